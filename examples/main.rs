@@ -38,12 +38,13 @@ struct Camera {
     projection: Mat4,
     view: Mat4,
     locked: bool,
+    actions: grafx::ActionMap,
 }
 
 impl Camera {
     fn new(ctx: &grafx::Context, speed: f32) -> Self {
-        ctx.set_cursor_position(ctx.size() / 2.0);
-        Self {
+        use grafx::{Binding::Key, KeyCode};
+        let mut camera = Self {
             position: vec3(0.0, 1.0, 2.0),
             direction: vec3(0.0, 0.0, -1.0),
             direction_interpolated: vec3(0.0, 0.0, -1.0),
@@ -53,7 +54,18 @@ impl Camera {
             yaw: -90.0f32.to_radians(),
             pitch: 0.0,
             locked: true,
-        }
+            actions: grafx::ActionMap::new()
+                .with_axis2d(
+                    "move",
+                    &[Key(KeyCode::KeyA)],
+                    &[Key(KeyCode::KeyD)],
+                    &[Key(KeyCode::KeyS)],
+                    &[Key(KeyCode::KeyW)],
+                )
+                .with_axis("fly", &[Key(KeyCode::ShiftLeft)], &[Key(KeyCode::Space)]),
+        };
+        camera.set_locked(ctx, true);
+        camera
     }
 
     fn update_projection(&mut self, size: Vec2) {
@@ -66,25 +78,27 @@ impl Camera {
         }
     }
 
-    fn key(&mut self, code: grafx::KeyCode, pressed: bool) {
-        match code {
-            grafx::KeyCode::Escape => {
-                if pressed {
-                    self.locked = !self.locked;
-                }
-            }
-            _ => {}
+    fn key(&mut self, ctx: &grafx::Context, code: grafx::KeyCode, pressed: bool) {
+        if code == grafx::KeyCode::Escape && pressed {
+            self.set_locked(ctx, !self.locked);
         }
     }
 
-    fn cursor(&mut self, ctx: &grafx::Context, pos: Vec2) {
+    fn set_locked(&mut self, ctx: &grafx::Context, locked: bool) {
+        self.locked = locked;
+        if locked {
+            ctx.lock_cursor();
+        } else {
+            ctx.unlock_cursor();
+        }
+        ctx.set_cursor_visible(!locked);
+    }
+
+    fn mouse_motion(&mut self, delta: Vec2) {
         if self.locked {
             let sensitivity = 0.002;
-            let center = ctx.size() / 2.0;
-            let cursor_delta = pos - center;
-            ctx.set_cursor_position(center);
-            self.yaw += cursor_delta.x * sensitivity;
-            self.pitch -= cursor_delta.y * sensitivity;
+            self.yaw += delta.x * sensitivity;
+            self.pitch -= delta.y * sensitivity;
 
             if self.pitch > 89.0f32.to_radians() {
                 self.pitch = 89.0f32.to_radians();
@@ -102,24 +116,10 @@ impl Camera {
     }
 
     fn update_view(&mut self, ctx: &grafx::Context, delta: f32) {
-        if ctx.key(grafx::KeyCode::KeyW) {
-            self.position += self.direction * self.speed * delta;
-        }
-        if ctx.key(grafx::KeyCode::KeyS) {
-            self.position -= self.direction * self.speed * delta;
-        }
-        if ctx.key(grafx::KeyCode::KeyA) {
-            self.position -= self.direction.cross(Vec3::Y).normalize() * self.speed * delta;
-        }
-        if ctx.key(grafx::KeyCode::KeyD) {
-            self.position += self.direction.cross(Vec3::Y).normalize() * self.speed * delta;
-        }
-        if ctx.key(grafx::KeyCode::Space) {
-            self.position += Vec3::Y * self.speed * delta;
-        }
-        if ctx.key(grafx::KeyCode::ShiftLeft) {
-            self.position -= Vec3::Y * self.speed * delta;
-        }
+        let movement = self.actions.axis2d(ctx.input(), "move");
+        let right = self.direction.cross(Vec3::Y).normalize();
+        self.position += (self.direction * movement.y + right * movement.x) * self.speed * delta;
+        self.position += Vec3::Y * self.actions.axis(ctx.input(), "fly") * self.speed * delta;
         self.direction_interpolated = self.direction_interpolated.lerp(self.direction, delta * 10.0);
         self.view = Mat4::look_to_rh(self.position, self.direction_interpolated, Vec3::Y);
     }
@@ -132,13 +132,9 @@ grafx::impl_uniform! {
 }
 
 struct Game {
-    pipeline: grafx::Pipeline,
-    vertex_buffer: grafx::VertexBuffer,
-    index_buffer: grafx::IndexBufferU16,
-    diffuse_bind_group: grafx::BindGroup,
+    bundle: grafx::RenderBundle,
     camera: Camera,
     camera_buffer: grafx::UniformBuffer,
-    camera_bind_group: grafx::BindGroup,
 }
 
 impl grafx::State for Game {
@@ -161,30 +157,43 @@ impl grafx::State for Game {
             .with(&camera_buffer)
             .build(ctx, &mut pipeline_builder);
         let pipeline = pipeline_builder.build(ctx);
-        Self {
+        let resources = (
             pipeline,
+            diffuse_bind_group,
+            camera_bind_group,
             vertex_buffer,
             index_buffer,
-            diffuse_bind_group,
+        );
+        let bundle = grafx::RenderBundle::new(ctx, resources, |resources, pass| {
+            let (pipeline, diffuse_bind_group, camera_bind_group, vertex_buffer, index_buffer) =
+                resources;
+            pipeline.attach(pass);
+            diffuse_bind_group.attach(pass, 0, &[]);
+            camera_bind_group.attach(pass, 1, &[]);
+            vertex_buffer.attach(pass, 0, ..);
+            index_buffer.attach(pass, ..);
+            pass.draw_indexed(0..6, 0, 0..1);
+        });
+        Self {
+            bundle,
             camera,
             camera_buffer,
-            camera_bind_group,
         }
     }
 
-    fn resize(&mut self, ctx: &grafx::Context, size: Vec2) {
+    fn resize(&mut self, ctx: &grafx::Context, size: Vec2, _logical_size: Vec2) {
         if size.x > 0.0 && size.y > 0.0 {
             self.camera.update_projection(size);
             self.camera_buffer.write(ctx, 0, &self.camera.uniform());
         }
     }
 
-    fn key(&mut self, _ctx: &grafx::Context, code: grafx::KeyCode, pressed: bool) {
-        self.camera.key(code, pressed);
+    fn key(&mut self, ctx: &grafx::Context, code: grafx::KeyCode, pressed: bool) {
+        self.camera.key(ctx, code, pressed);
     }
 
-    fn cursor(&mut self, ctx: &grafx::Context, pos: Vec2) {
-        self.camera.cursor(ctx, pos);
+    fn mouse_motion(&mut self, _ctx: &grafx::Context, delta: Vec2) {
+        self.camera.mouse_motion(delta);
     }
 
     fn update(&mut self, ctx: &grafx::Context, delta: Duration) {
@@ -194,12 +203,7 @@ impl grafx::State for Game {
 
     fn render(&self, frame: &mut grafx::Frame) {
         let mut pass = frame.pass(0.1, 0.2, 0.3, 1.0);
-        self.pipeline.attach(&mut pass);
-        self.diffuse_bind_group.attach(&mut pass, 0, &[]);
-        self.camera_bind_group.attach(&mut pass, 1, &[]);
-        self.vertex_buffer.attach(&mut pass, 0, ..);
-        self.index_buffer.attach(&mut pass, ..);
-        pass.draw_indexed(0..6, 0, 0..1);
+        pass.execute_bundles([&self.bundle]);
     }
 }
 
@@ -0,0 +1,494 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    path::Path,
+    str::FromStr,
+};
+
+use glam::*;
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::Input;
+
+/// Every key a binding can name, spelled like their `Debug` output in binding files.
+#[rustfmt::skip]
+const KEY_CODES: &[KeyCode] = {
+    use KeyCode::*;
+    &[
+        Backquote, Backslash, BracketLeft, BracketRight, Comma, Digit0, Digit1, Digit2, Digit3,
+        Digit4, Digit5, Digit6, Digit7, Digit8, Digit9, Equal, IntlBackslash, IntlRo, IntlYen,
+        KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ, KeyK, KeyL, KeyM, KeyN, KeyO,
+        KeyP, KeyQ, KeyR, KeyS, KeyT, KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ, Minus, Period, Quote,
+        Semicolon, Slash, AltLeft, AltRight, Backspace, CapsLock, ContextMenu, ControlLeft,
+        ControlRight, Enter, SuperLeft, SuperRight, ShiftLeft, ShiftRight, Space, Tab, Convert,
+        KanaMode, Lang1, Lang2, Lang3, Lang4, Lang5, NonConvert, Delete, End, Help, Home, Insert,
+        PageDown, PageUp, ArrowDown, ArrowLeft, ArrowRight, ArrowUp, NumLock, Numpad0, Numpad1,
+        Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9, NumpadAdd,
+        NumpadBackspace, NumpadClear, NumpadClearEntry, NumpadComma, NumpadDecimal, NumpadDivide,
+        NumpadEnter, NumpadEqual, NumpadHash, NumpadMemoryAdd, NumpadMemoryClear,
+        NumpadMemoryRecall, NumpadMemoryStore, NumpadMemorySubtract, NumpadMultiply,
+        NumpadParenLeft, NumpadParenRight, NumpadStar, NumpadSubtract, Escape, Fn, FnLock,
+        PrintScreen, ScrollLock, Pause, BrowserBack, BrowserFavorites, BrowserForward,
+        BrowserHome, BrowserRefresh, BrowserSearch, BrowserStop, Eject, LaunchApp1, LaunchApp2,
+        LaunchMail, MediaPlayPause, MediaSelect, MediaStop, MediaTrackNext, MediaTrackPrevious,
+        Power, Sleep, AudioVolumeDown, AudioVolumeMute, AudioVolumeUp, WakeUp, Meta, Hyper,
+        Turbo, Abort, Resume, Suspend, Again, Copy, Cut, Find, Open, Paste, Props, Select, Undo,
+        Hiragana, Katakana, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16,
+        F17, F18, F19, F20, F21, F22, F23, F24, F25, F26, F27, F28, F29, F30, F31, F32, F33, F34,
+        F35,
+    ]
+};
+
+/// Physical input an action or axis direction reacts to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+}
+
+impl Binding {
+    /// 0 or 1 for keys and buttons, the lines scrolled this update for the wheel.
+    fn value(self, input: &Input) -> f32 {
+        let scroll = input.scroll();
+        match self {
+            Binding::Key(code) => f32::from(u8::from(input.key(code))),
+            Binding::Mouse(button) => f32::from(u8::from(input.mouse_button(button))),
+            Binding::ScrollUp => scroll.y.max(0.0),
+            Binding::ScrollDown => (-scroll.y).max(0.0),
+            Binding::ScrollLeft => scroll.x.max(0.0),
+            Binding::ScrollRight => (-scroll.x).max(0.0),
+        }
+    }
+
+    /// Scrolling counts as a press on every update it happens in.
+    fn pressed(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(code) => input.key_pressed(code),
+            Binding::Mouse(button) => input.mouse_button_pressed(button),
+            _ => self.value(input) > 0.0,
+        }
+    }
+
+    fn released(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(code) => input.key_released(code),
+            Binding::Mouse(button) => input.mouse_button_released(button),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(code) => write!(f, "{code:?}"),
+            Binding::Mouse(MouseButton::Other(button)) => write!(f, "Mouse{button}"),
+            Binding::Mouse(button) => write!(f, "Mouse{button:?}"),
+            Binding::ScrollUp => write!(f, "ScrollUp"),
+            Binding::ScrollDown => write!(f, "ScrollDown"),
+            Binding::ScrollLeft => write!(f, "ScrollLeft"),
+            Binding::ScrollRight => write!(f, "ScrollRight"),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let binding = match name {
+            "MouseLeft" => Binding::Mouse(MouseButton::Left),
+            "MouseRight" => Binding::Mouse(MouseButton::Right),
+            "MouseMiddle" => Binding::Mouse(MouseButton::Middle),
+            "MouseBack" => Binding::Mouse(MouseButton::Back),
+            "MouseForward" => Binding::Mouse(MouseButton::Forward),
+            "ScrollUp" => Binding::ScrollUp,
+            "ScrollDown" => Binding::ScrollDown,
+            "ScrollLeft" => Binding::ScrollLeft,
+            "ScrollRight" => Binding::ScrollRight,
+            _ => {
+                if let Some(button) = name.strip_prefix("Mouse").and_then(|n| n.parse().ok()) {
+                    Binding::Mouse(MouseButton::Other(button))
+                } else {
+                    KEY_CODES
+                        .iter()
+                        .find(|code| format!("{code:?}") == name)
+                        .map(|&code| Binding::Key(code))
+                        .ok_or_else(|| format!("unknown binding {name:?}"))?
+                }
+            }
+        };
+        Ok(binding)
+    }
+}
+
+/// Bindings for each direction of an axis, negative before positive and x before y.
+#[derive(Clone, Debug)]
+struct Axis {
+    directions: Vec<Vec<Binding>>,
+    dead_zone: f32,
+}
+
+impl Axis {
+    fn value(&self, input: &Input) -> Vec2 {
+        let direction = |index: usize| {
+            self.directions.get(index).map_or(0.0, |bindings| {
+                bindings
+                    .iter()
+                    .map(|binding| binding.value(input))
+                    .fold(0.0, f32::max)
+            })
+        };
+        let value =
+            vec2(direction(1) - direction(0), direction(3) - direction(2)).clamp_length_max(1.0);
+        let length = value.length();
+        if length <= self.dead_zone {
+            Vec2::ZERO
+        } else {
+            value * ((length - self.dead_zone) / (1.0 - self.dead_zone) / length)
+        }
+    }
+}
+
+/// Names have to survive [`ActionMap::to_text`], which separates them by whitespace and starts
+/// comments with `#`.
+fn check_name(name: &str) {
+    assert!(
+        !name.is_empty() && !name.starts_with('#') && !name.contains(char::is_whitespace),
+        "{name:?} can't be saved as an action name"
+    );
+}
+
+/// Named actions and axes on top of [`Input`], so game logic doesn't refer to keys directly.
+/// Bindings can be changed at runtime and stored in a text file with one entry per line:
+///
+/// ```text
+/// action jump Space MouseLeft
+/// axis zoom 0 ScrollDown / ScrollUp
+/// axis2d move 0.1 KeyA ArrowLeft / KeyD ArrowRight / KeyS ArrowDown / KeyW ArrowUp
+/// ```
+///
+/// Unknown names read as unbound, with a warning the first time they are used. Names are single
+/// words, binding one that is empty, contains whitespace or starts with `#` panics.
+#[derive(Clone, Debug, Default)]
+pub struct ActionMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+    axes: BTreeMap<String, Axis>,
+    warned: RefCell<BTreeSet<String>>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_action(mut self, name: &str, bindings: &[Binding]) -> Self {
+        self.bind_action(name, bindings);
+        self
+    }
+
+    pub fn with_axis(mut self, name: &str, negative: &[Binding], positive: &[Binding]) -> Self {
+        self.bind_axis(name, negative, positive);
+        self
+    }
+
+    /// Composite axis such as WASD, its length limited to 1.
+    pub fn with_axis2d(
+        mut self,
+        name: &str,
+        left: &[Binding],
+        right: &[Binding],
+        down: &[Binding],
+        up: &[Binding],
+    ) -> Self {
+        self.bind_axis2d(name, left, right, down, up);
+        self
+    }
+
+    /// Values below `dead_zone` read as zero and the rest of the range is rescaled to 0..1.
+    pub fn with_dead_zone(mut self, name: &str, dead_zone: f32) -> Self {
+        self.set_dead_zone(name, dead_zone);
+        self
+    }
+
+    pub fn bind_action(&mut self, name: &str, bindings: &[Binding]) {
+        check_name(name);
+        self.actions.insert(name.to_owned(), bindings.to_vec());
+    }
+
+    pub fn bind_axis(&mut self, name: &str, negative: &[Binding], positive: &[Binding]) {
+        self.insert_axis(name, vec![negative.to_vec(), positive.to_vec()]);
+    }
+
+    pub fn bind_axis2d(
+        &mut self,
+        name: &str,
+        left: &[Binding],
+        right: &[Binding],
+        down: &[Binding],
+        up: &[Binding],
+    ) {
+        self.insert_axis(name, [left, right, down, up].map(<[_]>::to_vec).to_vec());
+    }
+
+    /// Keeps the dead zone of an axis that is rebound.
+    fn insert_axis(&mut self, name: &str, directions: Vec<Vec<Binding>>) {
+        check_name(name);
+        let dead_zone = self.axes.get(name).map_or(0.0, |axis| axis.dead_zone);
+        self.axes.insert(
+            name.to_owned(),
+            Axis {
+                directions,
+                dead_zone,
+            },
+        );
+    }
+
+    pub fn set_dead_zone(&mut self, name: &str, dead_zone: f32) {
+        match self.axes.get_mut(name) {
+            Some(axis) => axis.dead_zone = dead_zone.clamp(0.0, 0.99),
+            None => self.warn_unknown("axis", name),
+        }
+    }
+
+    fn warn_unknown(&self, kind: &str, name: &str) {
+        if self.warned.borrow_mut().insert(format!("{kind} {name}")) {
+            log::warn!("unknown {kind} {name:?}");
+        }
+    }
+
+    fn action_bindings(&self, name: &str) -> &[Binding] {
+        match self.actions.get(name) {
+            Some(bindings) => bindings,
+            None => {
+                self.warn_unknown("action", name);
+                &[]
+            }
+        }
+    }
+
+    fn axis_value(&self, input: &Input, name: &str) -> Vec2 {
+        match self.axes.get(name) {
+            Some(axis) => axis.value(input),
+            None => {
+                self.warn_unknown("axis", name);
+                Vec2::ZERO
+            }
+        }
+    }
+
+    /// Whether any binding of the action is held.
+    pub fn action(&self, input: &Input, name: &str) -> bool {
+        self.action_bindings(name)
+            .iter()
+            .any(|binding| binding.value(input) > 0.0)
+    }
+
+    pub fn action_pressed(&self, input: &Input, name: &str) -> bool {
+        self.action_bindings(name)
+            .iter()
+            .any(|binding| binding.pressed(input))
+    }
+
+    pub fn action_released(&self, input: &Input, name: &str) -> bool {
+        self.action_bindings(name)
+            .iter()
+            .any(|binding| binding.released(input))
+    }
+
+    /// Value of a one dimensional axis, from -1 to 1.
+    pub fn axis(&self, input: &Input, name: &str) -> f32 {
+        self.axis_value(input, name).x
+    }
+
+    pub fn axis2d(&self, input: &Input, name: &str) -> Vec2 {
+        self.axis_value(input, name)
+    }
+
+    pub fn to_text(&self) -> String {
+        let bindings = |bindings: &[Binding]| {
+            bindings
+                .iter()
+                .map(|binding| format!(" {binding}"))
+                .collect::<String>()
+        };
+        let mut text = String::new();
+        for (name, action) in &self.actions {
+            text += &format!("action {name}{}\n", bindings(action));
+        }
+        for (name, axis) in &self.axes {
+            let kind = if axis.directions.len() == 2 {
+                "axis"
+            } else {
+                "axis2d"
+            };
+            let directions = axis
+                .directions
+                .iter()
+                .map(|direction| bindings(direction))
+                .collect::<Vec<_>>()
+                .join(" /");
+            text += &format!("{kind} {name} {}{directions}\n", axis.dead_zone);
+        }
+        text
+    }
+
+    /// Replaces the bindings of every entry in `text`, leaving other entries as they are.
+    /// Invalid lines are skipped with a warning.
+    pub fn load_text(&mut self, text: &str) {
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Err(error) = self.load_line(line) {
+                log::warn!("skipping line {} of action bindings: {error}", index + 1);
+            }
+        }
+    }
+
+    fn load_line(&mut self, line: &str) -> Result<(), String> {
+        let mut tokens = line.split_whitespace();
+        let kind = tokens.next().unwrap();
+        let name = tokens.next().ok_or("missing name")?;
+        if kind == "action" {
+            let bindings = tokens.map(str::parse).collect::<Result<Vec<_>, _>>()?;
+            self.bind_action(name, &bindings);
+            return Ok(());
+        }
+        let direction_count = match kind {
+            "axis" => 2,
+            "axis2d" => 4,
+            _ => return Err(format!("unknown kind {kind:?}")),
+        };
+        let dead_zone = tokens
+            .next()
+            .and_then(|token| token.parse().ok())
+            .ok_or("missing dead zone")?;
+        let rest = tokens.collect::<Vec<_>>();
+        let directions = rest
+            .split(|&token| token == "/")
+            .map(|direction| direction.iter().map(|token| token.parse()).collect())
+            .collect::<Result<Vec<Vec<Binding>>, _>>()?;
+        if directions.len() != direction_count {
+            return Err(format!("{kind} needs {direction_count} directions"));
+        }
+        self.insert_axis(name, directions);
+        self.set_dead_zone(name, dead_zone);
+        Ok(())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    /// Loads bindings saved with [`ActionMap::save`] over the current ones, keeping them all if
+    /// the file can't be read.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.load_text(&std::fs::read_to_string(path)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        let actions = ActionMap::new()
+            .with_action(
+                "jump",
+                &[
+                    Binding::Key(KeyCode::Space),
+                    Binding::Mouse(MouseButton::Left),
+                ],
+            )
+            .with_action("use", &[Binding::Mouse(MouseButton::Other(7))])
+            .with_action("none", &[])
+            .with_axis("zoom", &[Binding::ScrollDown], &[Binding::ScrollUp])
+            .with_axis2d(
+                "move",
+                &[
+                    Binding::Key(KeyCode::KeyA),
+                    Binding::Key(KeyCode::ArrowLeft),
+                ],
+                &[Binding::Key(KeyCode::KeyD)],
+                &[Binding::Key(KeyCode::KeyS)],
+                &[],
+            )
+            .with_dead_zone("move", 0.25);
+        let text = actions.to_text();
+        let mut loaded = ActionMap::new();
+        loaded.load_text(&text);
+        assert_eq!(loaded.to_text(), text);
+        assert_eq!(loaded.actions, actions.actions);
+        assert_eq!(
+            loaded.axes["move"].directions,
+            actions.axes["move"].directions
+        );
+        assert_eq!(loaded.axes["move"].dead_zone, 0.25);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let actions = ActionMap::new()
+            .with_action("jump-high", &[Binding::Key(KeyCode::Space)])
+            .with_axis("zoom.level", &[Binding::ScrollDown], &[Binding::ScrollUp]);
+        let path = std::env::temp_dir().join(format!("grafx-{}-actions", std::process::id()));
+        actions.save(&path).unwrap();
+        let mut loaded = ActionMap::new();
+        loaded.load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.actions, actions.actions);
+        assert_eq!(
+            loaded.axes["zoom.level"].directions,
+            actions.axes["zoom.level"].directions
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be saved as an action name")]
+    fn names_with_whitespace_are_rejected() {
+        ActionMap::new().bind_action("jump high", &[Binding::Key(KeyCode::Space)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "can't be saved as an action name")]
+    fn empty_names_are_rejected() {
+        ActionMap::new().bind_axis("", &[], &[]);
+    }
+
+    #[test]
+    fn invalid_lines_are_skipped() {
+        let mut actions = ActionMap::new().with_action("jump", &[Binding::Key(KeyCode::Space)]);
+        actions.load_text(
+            "# comment\n\
+             action jump KeyJ NoSuchKey\n\
+             axis zoom x ScrollDown / ScrollUp\n\
+             axis2d move 0 KeyA / KeyD\n\
+             action fire MouseRight\n",
+        );
+        assert_eq!(actions.actions["jump"], [Binding::Key(KeyCode::Space)]);
+        assert_eq!(
+            actions.actions["fire"],
+            [Binding::Mouse(MouseButton::Right)]
+        );
+        assert!(actions.axes.is_empty());
+    }
+
+    #[test]
+    fn unknown_names_are_unbound() {
+        let input = Input::default();
+        let actions = ActionMap::new();
+        assert!(!actions.action(&input, "jupm"));
+        assert!(!actions.action_pressed(&input, "jupm"));
+        assert_eq!(actions.axis(&input, "zoom"), 0.0);
+        assert_eq!(actions.axis2d(&input, "move"), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use glam::*;
+use image::{DynamicImage, GenericImage, Rgba32FImage};
+
+use crate::{Context, Texture2D, Texture2DBuilder};
+
+/// Packs loose images into a single texture, shelf by shelf from the tallest image down.
+pub struct AtlasBuilder {
+    images: Vec<(String, DynamicImage)>,
+    padding: u32,
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            padding: 1,
+        }
+    }
+
+    /// Empty pixels between images, one by default so filtering doesn't bleed into neighbours.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Decodes an image file and adds it under `name`.
+    pub fn with(self, name: impl Into<String>, data: &[u8]) -> Self {
+        self.with_image(name, image::load_from_memory(data).unwrap())
+    }
+
+    pub fn with_image(mut self, name: impl Into<String>, image: DynamicImage) -> Self {
+        self.images.push((name.into(), image));
+        self
+    }
+
+    /// Places every image and returns the atlas size with the pixel position of each image.
+    fn pack(&self) -> (UVec2, Vec<UVec2>) {
+        let padded = |image: &DynamicImage| uvec2(image.width(), image.height()) + self.padding;
+        let area = self
+            .images
+            .iter()
+            .map(|(_, image)| padded(image).element_product())
+            .sum::<u32>();
+        let widest = self
+            .images
+            .iter()
+            .map(|(_, image)| padded(image).x)
+            .max()
+            .unwrap_or(1);
+        let width = ((area as f32).sqrt().ceil() as u32)
+            .max(widest)
+            .next_power_of_two();
+        let mut order = (0..self.images.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&index| std::cmp::Reverse(self.images[index].1.height()));
+        let mut positions = vec![UVec2::ZERO; self.images.len()];
+        let mut cursor = UVec2::ZERO;
+        let mut shelf_height = 0;
+        for index in order {
+            let size = padded(&self.images[index].1);
+            if cursor.x + size.x > width {
+                cursor = uvec2(0, cursor.y + shelf_height);
+                shelf_height = 0;
+            }
+            positions[index] = cursor;
+            cursor.x += size.x;
+            shelf_height = shelf_height.max(size.y);
+        }
+        let height = (cursor.y + shelf_height).max(1).next_power_of_two();
+        (uvec2(width, height), positions)
+    }
+
+    /// Builds an sRGB atlas without mipmaps.
+    pub fn build(self, ctx: &Context) -> Atlas {
+        self.build_with(ctx, Texture2DBuilder::new().with_mipmaps(false))
+    }
+
+    pub fn build_with(self, ctx: &Context, builder: Texture2DBuilder) -> Atlas {
+        let (size, positions) = self.pack();
+        let mut image = Rgba32FImage::new(size.x, size.y);
+        let mut rects = HashMap::new();
+        for ((name, source), position) in self.images.into_iter().zip(positions) {
+            image
+                .copy_from(&source.to_rgba32f(), position.x, position.y)
+                .unwrap();
+            let min = position.as_vec2() / size.as_vec2();
+            let max =
+                (position + uvec2(source.width(), source.height())).as_vec2() / size.as_vec2();
+            rects.insert(name, (min, max));
+        }
+        Atlas {
+            texture: builder.build_from_image(ctx, DynamicImage::ImageRgba32F(image)),
+            rects,
+        }
+    }
+}
+
+pub struct Atlas {
+    pub texture: Texture2D,
+    rects: HashMap<String, (Vec2, Vec2)>,
+}
+
+impl Atlas {
+    /// Top left and bottom right texture coordinates of the image added as `name`.
+    pub fn uv(&self, name: &str) -> Option<(Vec2, Vec2)> {
+        self.rects.get(name).copied()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rects.keys().map(String::as_str)
+    }
+}
@@ -1,27 +1,62 @@
-use crate::{Context, Pass, PipelineBuilder, Sampler, Texture2D, UniformBuffer};
+use crate::{
+    buffer::IndirectArgs, ComputePass, ComputePipelineBuilder, Context, DepthTexture,
+    IndirectBuffer, Pass, PipelineBuilder, Sampler, StorageView, Texture2D, Texture2DArray,
+    Texture3D, TextureCube, UniformBuffer,
+};
 
 pub(crate) trait BindGroupEntry {
     fn visibility(&self) -> wgpu::ShaderStages;
     fn binding_type(&self) -> wgpu::BindingType;
-    fn resource(&self) -> wgpu::BindingResource;
+    fn resource(&self) -> wgpu::BindingResource<'_>;
 }
 
 pub struct BindGroup(wgpu::BindGroup);
 
 impl BindGroup {
     pub fn attach<'a>(&'a self, pass: &mut Pass<'a>, index: u32, offsets: &[u32]) {
+        pass.encoder().set_bind_group(index, &self.0, offsets);
+    }
+
+    pub fn attach_compute<'a>(&'a self, pass: &mut ComputePass<'a>, index: u32, offsets: &[u32]) {
         pass.0.set_bind_group(index, &self.0, offsets);
     }
 }
 
 pub struct BindGroupBuilder<'a>(Vec<&'a dyn BindGroupEntry>);
 
+impl<'a> Default for BindGroupBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> BindGroupBuilder<'a> {
     pub fn new() -> Self {
         Self(Vec::new())
     }
 
     pub fn build(self, ctx: &Context, pipeline_builder: &mut PipelineBuilder) -> BindGroup {
+        let (layout, bind_group) = self.create(ctx);
+        pipeline_builder.with_bind_group_layout(layout);
+        bind_group
+    }
+
+    pub fn build_compute(
+        self,
+        ctx: &Context,
+        pipeline_builder: &mut ComputePipelineBuilder,
+    ) -> BindGroup {
+        let (layout, bind_group) = self.create(ctx);
+        pipeline_builder.with_bind_group_layout(layout);
+        bind_group
+    }
+
+    /// Builds a bind group for a slot whose layout an identical builder already registered.
+    pub fn build_shared(self, ctx: &Context) -> BindGroup {
+        self.create(ctx).1
+    }
+
+    fn create(self, ctx: &Context) -> (wgpu::BindGroupLayout, BindGroup) {
         let layout_entries = self
             .0
             .iter()
@@ -55,8 +90,7 @@ impl<'a> BindGroupBuilder<'a> {
             layout: &layout,
             entries: &entries,
         });
-        pipeline_builder.with_bind_group_layout(layout);
-        BindGroup(bind_group)
+        (layout, BindGroup(bind_group))
     }
 }
 
@@ -71,6 +105,41 @@ impl<'a> BindGroupBuilderWith<'a, Texture2D> for BindGroupBuilder<'a> {
     }
 }
 
+impl<'a> BindGroupBuilderWith<'a, Texture2DArray> for BindGroupBuilder<'a> {
+    fn with(mut self, data: &'a Texture2DArray) -> Self {
+        self.0.push(data);
+        self
+    }
+}
+
+impl<'a> BindGroupBuilderWith<'a, Texture3D> for BindGroupBuilder<'a> {
+    fn with(mut self, data: &'a Texture3D) -> Self {
+        self.0.push(data);
+        self
+    }
+}
+
+impl<'a> BindGroupBuilderWith<'a, TextureCube> for BindGroupBuilder<'a> {
+    fn with(mut self, data: &'a TextureCube) -> Self {
+        self.0.push(data);
+        self
+    }
+}
+
+impl<'a> BindGroupBuilderWith<'a, DepthTexture> for BindGroupBuilder<'a> {
+    fn with(mut self, data: &'a DepthTexture) -> Self {
+        self.0.push(data);
+        self
+    }
+}
+
+impl<'a> BindGroupBuilderWith<'a, StorageView> for BindGroupBuilder<'a> {
+    fn with(mut self, data: &'a StorageView) -> Self {
+        self.0.push(data);
+        self
+    }
+}
+
 impl<'a> BindGroupBuilderWith<'a, Sampler> for BindGroupBuilder<'a> {
     fn with(mut self, data: &'a Sampler) -> Self {
         self.0.push(data);
@@ -84,3 +153,10 @@ impl<'a> BindGroupBuilderWith<'a, UniformBuffer> for BindGroupBuilder<'a> {
         self
     }
 }
+
+impl<'a, T: IndirectArgs> BindGroupBuilderWith<'a, IndirectBuffer<T>> for BindGroupBuilder<'a> {
+    fn with(mut self, data: &'a IndirectBuffer<T>) -> Self {
+        self.0.push(data);
+        self
+    }
+}
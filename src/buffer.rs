@@ -1,8 +1,8 @@
-use std::ops::RangeBounds;
+use std::{marker::PhantomData, ops::RangeBounds};
 
 use wgpu::util::DeviceExt;
 
-use crate::{uniform, vertex, BindGroupEntry, BufferAddress, Context, Pass};
+use crate::{data, uniform, vertex, BindGroupEntry, BufferAddress, Context, Pass};
 
 pub struct UniformBuffer(wgpu::Buffer);
 
@@ -26,7 +26,7 @@ impl UniformBuffer {
 
 impl BindGroupEntry for UniformBuffer {
     fn visibility(&self) -> wgpu::ShaderStages {
-        wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT
+        wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE
     }
 
     fn binding_type(&self) -> wgpu::BindingType {
@@ -37,7 +37,7 @@ impl BindGroupEntry for UniformBuffer {
         }
     }
 
-    fn resource(&self) -> wgpu::BindingResource {
+    fn resource(&self) -> wgpu::BindingResource<'_> {
         self.0.as_entire_binding()
     }
 }
@@ -62,7 +62,7 @@ impl VertexBuffer {
         slot: u32,
         bounds: B,
     ) {
-        pass.0.set_vertex_buffer(slot, self.0.slice(bounds));
+        pass.encoder().set_vertex_buffer(slot, self.0.slice(bounds));
     }
 }
 
@@ -81,7 +81,7 @@ impl IndexBufferU16 {
     }
 
     pub fn attach<'a, B: RangeBounds<BufferAddress>>(&'a self, pass: &mut Pass<'a>, bounds: B) {
-        pass.0
+        pass.encoder()
             .set_index_buffer(self.0.slice(bounds), wgpu::IndexFormat::Uint16);
     }
 }
@@ -101,7 +101,102 @@ impl IndexBufferU32 {
     }
 
     pub fn attach<'a, B: RangeBounds<BufferAddress>>(&'a self, pass: &mut Pass<'a>, bounds: B) {
-        pass.0
+        pass.encoder()
             .set_index_buffer(self.0.slice(bounds), wgpu::IndexFormat::Uint32);
     }
 }
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, data::Pod, data::Zeroable)]
+pub struct DrawIndirectArgs {
+    pub vertex_count: u32,
+    pub instance_count: u32,
+    pub first_vertex: u32,
+    pub first_instance: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, data::Pod, data::Zeroable)]
+pub struct DrawIndexedIndirectArgs {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub first_instance: u32,
+}
+
+pub trait IndirectArgs: data::Pod + data::Zeroable {}
+
+impl IndirectArgs for DrawIndirectArgs {}
+
+impl IndirectArgs for DrawIndexedIndirectArgs {}
+
+/// Draw arguments read by the GPU, writable from compute shaders as a storage buffer.
+pub struct IndirectBuffer<T> {
+    pub(crate) buffer: wgpu::Buffer,
+    len: u32,
+    _marker: PhantomData<T>,
+}
+
+impl<T: IndirectArgs> IndirectBuffer<T> {
+    pub fn new(ctx: &Context, data: &[T]) -> Self {
+        Self {
+            buffer: ctx
+                .device()
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(data),
+                    usage: wgpu::BufferUsages::INDIRECT
+                        | wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::COPY_DST,
+                }),
+            len: data.len() as u32,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Replaces the arguments starting at `index`.
+    pub fn write(&self, ctx: &Context, index: u32, data: &[T]) {
+        assert!(
+            index as usize + data.len() <= self.len as usize,
+            "writing {} draws at {index} overflows an indirect buffer of {}",
+            data.len(),
+            self.len
+        );
+        ctx.queue().write_buffer(
+            &self.buffer,
+            Self::offset(index),
+            bytemuck::cast_slice(data),
+        );
+    }
+
+    pub(crate) fn offset(index: u32) -> BufferAddress {
+        index as BufferAddress * std::mem::size_of::<T>() as BufferAddress
+    }
+}
+
+impl<T: IndirectArgs> BindGroupEntry for IndirectBuffer<T> {
+    fn visibility(&self) -> wgpu::ShaderStages {
+        wgpu::ShaderStages::COMPUTE
+    }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        }
+    }
+
+    fn resource(&self) -> wgpu::BindingResource<'_> {
+        self.buffer.as_entire_binding()
+    }
+}
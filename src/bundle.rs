@@ -0,0 +1,82 @@
+use elsa::FrozenMap;
+
+use crate::{frame::Target, pass::Encoder, pipeline::BundleSlot, Context, Pass};
+
+trait Record {
+    fn record<'a>(&'a self, pass: &mut Pass<'a>);
+}
+
+struct Recorder<R, F> {
+    resources: R,
+    record: F,
+}
+
+impl<R, F: for<'a> Fn(&'a R, &mut Pass<'a>)> Record for Recorder<R, F> {
+    fn record<'a>(&'a self, pass: &mut Pass<'a>) {
+        (self.record)(&self.resources, pass);
+    }
+}
+
+struct Recorded {
+    bundle: wgpu::RenderBundle,
+    /// Keeps the push constant values of the bundle alive.
+    _push_constant_slots: Vec<BundleSlot>,
+}
+
+/// Draw commands recorded once and replayed with [`Pass::execute_bundles`]. The bundle owns the
+/// resources it draws, so it can record the commands again for a pass whose target has other
+/// formats, e.g. after [`Context::set_depth_format`] or when drawing into a
+/// [`RenderTarget`](crate::RenderTarget).
+pub struct RenderBundle {
+    record: Box<dyn Record>,
+    recorded: FrozenMap<Target, Box<Recorded>>,
+}
+
+impl RenderBundle {
+    /// Records `record` for the window, passing it the resources.
+    pub fn new<R: 'static>(
+        ctx: &Context,
+        resources: R,
+        record: impl for<'a> Fn(&'a R, &mut Pass<'a>) + 'static,
+    ) -> Self {
+        let bundle = Self {
+            record: Box::new(Recorder { resources, record }),
+            recorded: FrozenMap::new(),
+        };
+        bundle.get(ctx, Target::window(ctx));
+        bundle
+    }
+
+    /// Bundle recorded for `target`, recording it on first use.
+    pub(crate) fn get(&self, ctx: &Context, target: Target) -> &wgpu::RenderBundle {
+        if let Some(recorded) = self.recorded.get(&target) {
+            return &recorded.bundle;
+        }
+        let encoder =
+            ctx.device()
+                .create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                    label: None,
+                    color_formats: &[Some(target.format)],
+                    depth_stencil: target.depth_format.map(|format| {
+                        wgpu::RenderBundleDepthStencil {
+                            format: format.into(),
+                            depth_read_only: false,
+                            stencil_read_only: false,
+                        }
+                    }),
+                    sample_count: target.sample_count,
+                    multiview: None,
+                });
+        let mut pass = Pass::bundle(ctx, encoder, target);
+        self.record.record(&mut pass);
+        let bundle = match pass.raw {
+            Encoder::Bundle(encoder) => encoder.finish(&wgpu::RenderBundleDescriptor::default()),
+            Encoder::Pass(_) => unreachable!(),
+        };
+        let recorded = Recorded {
+            bundle,
+            _push_constant_slots: pass.bundle_slots,
+        };
+        &self.recorded.insert(target, Box::new(recorded)).bundle
+    }
+}
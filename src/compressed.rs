@@ -0,0 +1,1539 @@
+use std::fmt;
+
+use wgpu::{AstcBlock, AstcChannel, TextureFormat as Format};
+
+use crate::{texture::f16_to_f32, texture::f32_to_f16, Context, TextureFormat};
+
+/// Why a KTX2 or DDS file can't be loaded.
+#[derive(Debug)]
+pub enum CompressedTextureError {
+    Ktx2(ktx2::ParseError),
+    Dds(ddsfile::Error),
+    /// KTX2 supercompression, e.g. Basis Universal, is not supported.
+    Supercompressed,
+    /// The file doesn't hold one of the block compressed formats.
+    UnsupportedFormat,
+    /// The file ends before the last level does.
+    Truncated,
+    /// The file claims more mip levels than a full chain of its size has.
+    TooManyLevels(u32),
+    /// The adapter doesn't support the format and the CPU can't decode it, e.g. ASTC.
+    Undecodable(Format),
+}
+
+impl fmt::Display for CompressedTextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ktx2(error) => write!(f, "invalid KTX2 file: {error}"),
+            Self::Dds(error) => write!(f, "invalid DDS file: {error}"),
+            Self::Supercompressed => write!(f, "supercompressed KTX2 files are not supported"),
+            Self::UnsupportedFormat => write!(f, "the file is not block compressed"),
+            Self::Truncated => write!(f, "the file is truncated"),
+            Self::TooManyLevels(count) => {
+                write!(
+                    f,
+                    "{count} mip levels are more than the texture size allows"
+                )
+            }
+            Self::Undecodable(format) => write!(
+                f,
+                "{format:?} is not supported by the adapter nor by the CPU decoder"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompressedTextureError {}
+
+impl From<ktx2::ParseError> for CompressedTextureError {
+    fn from(error: ktx2::ParseError) -> Self {
+        Self::Ktx2(error)
+    }
+}
+
+impl From<ddsfile::Error> for CompressedTextureError {
+    fn from(error: ddsfile::Error) -> Self {
+        Self::Dds(error)
+    }
+}
+
+/// Levels of a block compressed texture from a KTX2 or DDS file, largest first. Only the first
+/// layer or face is kept.
+pub(crate) struct CompressedImage {
+    pub(crate) format: Format,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) levels: Vec<Vec<u8>>,
+}
+
+fn level_size(format: Format, width: u32, height: u32, level: u32) -> usize {
+    let (block_width, block_height) = format.block_dimensions();
+    let columns = (width >> level).max(1).div_ceil(block_width);
+    let rows = (height >> level).max(1).div_ceil(block_height);
+    (columns * rows * format.block_copy_size(None).unwrap()) as usize
+}
+
+/// Levels of a full mip chain, down to 1x1.
+fn max_level_count(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+impl CompressedImage {
+    pub(crate) fn from_ktx2(data: &[u8]) -> Result<Self, CompressedTextureError> {
+        let reader = ktx2::Reader::new(data)?;
+        let header = reader.header();
+        if header.supercompression_scheme.is_some() {
+            return Err(CompressedTextureError::Supercompressed);
+        }
+        let format = header
+            .format
+            .and_then(ktx2_format)
+            .ok_or(CompressedTextureError::UnsupportedFormat)?;
+        let (width, height) = (header.pixel_width, header.pixel_height.max(1));
+        let level_count = reader.levels().len() as u32;
+        if level_count > max_level_count(width, height) {
+            return Err(CompressedTextureError::TooManyLevels(level_count));
+        }
+        let levels = reader
+            .levels()
+            .enumerate()
+            .map(|(level, data)| {
+                data.data
+                    .get(..level_size(format, width, height, level as u32))
+                    .map(<[u8]>::to_vec)
+                    .ok_or(CompressedTextureError::Truncated)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            format,
+            width,
+            height,
+            levels,
+        })
+    }
+
+    pub(crate) fn from_dds(data: &[u8]) -> Result<Self, CompressedTextureError> {
+        let mut dds = ddsfile::Dds::read(data)?;
+        let format = dds
+            .get_d3d_format()
+            .and_then(d3d_format)
+            .or_else(|| dds.get_dxgi_format().and_then(dxgi_format))
+            .ok_or(CompressedTextureError::UnsupportedFormat)?;
+        let (width, height) = (dds.get_width(), dds.get_height());
+        // A count of 0 means only the main image, as does a missing one.
+        let level_count = dds.get_num_mipmap_levels().max(1);
+        dds.header.mip_map_count = Some(level_count);
+        if level_count > max_level_count(width, height) {
+            return Err(CompressedTextureError::TooManyLevels(level_count));
+        }
+        let mut data = dds.get_data(0)?;
+        let levels = (0..level_count)
+            .map(|level| {
+                let size = level_size(format, width, height, level);
+                if data.len() < size {
+                    return Err(CompressedTextureError::Truncated);
+                }
+                let (level, rest) = data.split_at(size);
+                data = rest;
+                Ok(level.to_vec())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            format,
+            width,
+            height,
+            levels,
+        })
+    }
+
+    /// Whether the adapter can sample the format. Block compressed textures also need a size
+    /// that is a multiple of the block size.
+    pub(crate) fn is_supported(&self, ctx: &Context) -> bool {
+        let (block_width, block_height) = self.format.block_dimensions();
+        ctx.features().contains(self.format.required_features())
+            && self.width.is_multiple_of(block_width)
+            && self.height.is_multiple_of(block_height)
+    }
+
+    /// Decodes every level to tightly packed pixels of the returned format: RGBA8 for the
+    /// unsigned formats, half floats for BC6H and the signed ones.
+    pub(crate) fn decompress(
+        &self,
+    ) -> Result<(TextureFormat, Vec<Vec<u8>>), CompressedTextureError> {
+        let decoder =
+            block_decoder(self.format).ok_or(CompressedTextureError::Undecodable(self.format))?;
+        let format = match decoder {
+            Decoder::Unorm(_) if self.format.is_srgb() => TextureFormat::Rgba8UnormSrgb,
+            Decoder::Unorm(_) => TextureFormat::Rgba8Unorm,
+            Decoder::Float(_) => TextureFormat::Rgba16Float,
+        };
+        let levels = (0..self.levels.len())
+            .map(|level| match decoder {
+                Decoder::Unorm(decode) => self.decompress_level(level, decode),
+                Decoder::Float(decode) => self.decompress_level(level, |block| {
+                    decode(block).map(|color| {
+                        let mut bytes = [0; 8];
+                        for (channel, value) in color.into_iter().enumerate() {
+                            bytes[channel * 2..channel * 2 + 2]
+                                .copy_from_slice(&f32_to_f16(value).to_le_bytes());
+                        }
+                        bytes
+                    })
+                }),
+            })
+            .collect();
+        Ok((format, levels))
+    }
+
+    fn decompress_level<const N: usize>(
+        &self,
+        level: usize,
+        decode: impl Fn(&[u8]) -> [[u8; N]; 16],
+    ) -> Vec<u8> {
+        let block_size = self.format.block_copy_size(None).unwrap() as usize;
+        let width = (self.width >> level).max(1);
+        let height = (self.height >> level).max(1);
+        let columns = width.div_ceil(4);
+        let mut pixels = vec![0; width as usize * height as usize * N];
+        for (index, block) in self.levels[level].chunks_exact(block_size).enumerate() {
+            let column = index as u32 % columns;
+            let row = index as u32 / columns;
+            for (texel, color) in decode(block).iter().enumerate() {
+                let x = column * 4 + texel as u32 % 4;
+                let y = row * 4 + texel as u32 / 4;
+                if x < width && y < height {
+                    let offset = (y * width + x) as usize * N;
+                    pixels[offset..offset + N].copy_from_slice(color);
+                }
+            }
+        }
+        pixels
+    }
+}
+
+const ASTC_BLOCKS: [AstcBlock; 14] = [
+    AstcBlock::B4x4,
+    AstcBlock::B5x4,
+    AstcBlock::B5x5,
+    AstcBlock::B6x5,
+    AstcBlock::B6x6,
+    AstcBlock::B8x5,
+    AstcBlock::B8x6,
+    AstcBlock::B8x8,
+    AstcBlock::B10x5,
+    AstcBlock::B10x6,
+    AstcBlock::B10x8,
+    AstcBlock::B10x10,
+    AstcBlock::B12x10,
+    AstcBlock::B12x12,
+];
+
+fn ktx2_format(format: ktx2::Format) -> Option<Format> {
+    use ktx2::Format as Ktx2;
+    Some(match format {
+        Ktx2::BC1_RGB_UNORM_BLOCK | Ktx2::BC1_RGBA_UNORM_BLOCK => Format::Bc1RgbaUnorm,
+        Ktx2::BC1_RGB_SRGB_BLOCK | Ktx2::BC1_RGBA_SRGB_BLOCK => Format::Bc1RgbaUnormSrgb,
+        Ktx2::BC2_UNORM_BLOCK => Format::Bc2RgbaUnorm,
+        Ktx2::BC2_SRGB_BLOCK => Format::Bc2RgbaUnormSrgb,
+        Ktx2::BC3_UNORM_BLOCK => Format::Bc3RgbaUnorm,
+        Ktx2::BC3_SRGB_BLOCK => Format::Bc3RgbaUnormSrgb,
+        Ktx2::BC4_UNORM_BLOCK => Format::Bc4RUnorm,
+        Ktx2::BC4_SNORM_BLOCK => Format::Bc4RSnorm,
+        Ktx2::BC5_UNORM_BLOCK => Format::Bc5RgUnorm,
+        Ktx2::BC5_SNORM_BLOCK => Format::Bc5RgSnorm,
+        Ktx2::BC6H_UFLOAT_BLOCK => Format::Bc6hRgbUfloat,
+        Ktx2::BC6H_SFLOAT_BLOCK => Format::Bc6hRgbFloat,
+        Ktx2::BC7_UNORM_BLOCK => Format::Bc7RgbaUnorm,
+        Ktx2::BC7_SRGB_BLOCK => Format::Bc7RgbaUnormSrgb,
+        Ktx2::ETC2_R8G8B8_UNORM_BLOCK => Format::Etc2Rgb8Unorm,
+        Ktx2::ETC2_R8G8B8_SRGB_BLOCK => Format::Etc2Rgb8UnormSrgb,
+        Ktx2::ETC2_R8G8B8A1_UNORM_BLOCK => Format::Etc2Rgb8A1Unorm,
+        Ktx2::ETC2_R8G8B8A1_SRGB_BLOCK => Format::Etc2Rgb8A1UnormSrgb,
+        Ktx2::ETC2_R8G8B8A8_UNORM_BLOCK => Format::Etc2Rgba8Unorm,
+        Ktx2::ETC2_R8G8B8A8_SRGB_BLOCK => Format::Etc2Rgba8UnormSrgb,
+        Ktx2::EAC_R11_UNORM_BLOCK => Format::EacR11Unorm,
+        Ktx2::EAC_R11_SNORM_BLOCK => Format::EacR11Snorm,
+        Ktx2::EAC_R11G11_UNORM_BLOCK => Format::EacRg11Unorm,
+        Ktx2::EAC_R11G11_SNORM_BLOCK => Format::EacRg11Snorm,
+        _ => match format.value() {
+            // Unorm and sRGB variants alternate for every block size.
+            value @ 157..=184 => Format::Astc {
+                block: ASTC_BLOCKS[(value - 157) as usize / 2],
+                channel: if (value - 157) % 2 == 0 {
+                    AstcChannel::Unorm
+                } else {
+                    AstcChannel::UnormSrgb
+                },
+            },
+            value @ 1000066000..=1000066013 => Format::Astc {
+                block: ASTC_BLOCKS[(value - 1000066000) as usize],
+                channel: AstcChannel::Hdr,
+            },
+            _ => return None,
+        },
+    })
+}
+
+fn d3d_format(format: ddsfile::D3DFormat) -> Option<Format> {
+    use ddsfile::D3DFormat;
+    Some(match format {
+        D3DFormat::DXT1 => Format::Bc1RgbaUnorm,
+        D3DFormat::DXT2 | D3DFormat::DXT3 => Format::Bc2RgbaUnorm,
+        D3DFormat::DXT4 | D3DFormat::DXT5 => Format::Bc3RgbaUnorm,
+        _ => return None,
+    })
+}
+
+fn dxgi_format(format: ddsfile::DxgiFormat) -> Option<Format> {
+    use ddsfile::DxgiFormat;
+    Some(match format {
+        DxgiFormat::BC1_Typeless | DxgiFormat::BC1_UNorm => Format::Bc1RgbaUnorm,
+        DxgiFormat::BC1_UNorm_sRGB => Format::Bc1RgbaUnormSrgb,
+        DxgiFormat::BC2_Typeless | DxgiFormat::BC2_UNorm => Format::Bc2RgbaUnorm,
+        DxgiFormat::BC2_UNorm_sRGB => Format::Bc2RgbaUnormSrgb,
+        DxgiFormat::BC3_Typeless | DxgiFormat::BC3_UNorm => Format::Bc3RgbaUnorm,
+        DxgiFormat::BC3_UNorm_sRGB => Format::Bc3RgbaUnormSrgb,
+        DxgiFormat::BC4_Typeless | DxgiFormat::BC4_UNorm => Format::Bc4RUnorm,
+        DxgiFormat::BC4_SNorm => Format::Bc4RSnorm,
+        DxgiFormat::BC5_Typeless | DxgiFormat::BC5_UNorm => Format::Bc5RgUnorm,
+        DxgiFormat::BC5_SNorm => Format::Bc5RgSnorm,
+        DxgiFormat::BC6H_Typeless | DxgiFormat::BC6H_UF16 => Format::Bc6hRgbUfloat,
+        DxgiFormat::BC6H_SF16 => Format::Bc6hRgbFloat,
+        DxgiFormat::BC7_Typeless | DxgiFormat::BC7_UNorm => Format::Bc7RgbaUnorm,
+        DxgiFormat::BC7_UNorm_sRGB => Format::Bc7RgbaUnormSrgb,
+        _ => return None,
+    })
+}
+
+type Block = [[u8; 4]; 16];
+type FloatBlock = [[f32; 4]; 16];
+
+#[derive(Clone, Copy)]
+enum Decoder {
+    Unorm(fn(&[u8]) -> Block),
+    Float(fn(&[u8]) -> FloatBlock),
+}
+
+/// CPU decoders for the BC, ETC2 and EAC formats, ASTC needs the adapter.
+fn block_decoder(format: Format) -> Option<Decoder> {
+    Some(match format {
+        Format::Bc1RgbaUnorm | Format::Bc1RgbaUnormSrgb => {
+            Decoder::Unorm(|block| decode_bc1(block, true))
+        }
+        Format::Bc2RgbaUnorm | Format::Bc2RgbaUnormSrgb => Decoder::Unorm(decode_bc2),
+        Format::Bc3RgbaUnorm | Format::Bc3RgbaUnormSrgb => Decoder::Unorm(decode_bc3),
+        Format::Bc4RUnorm => Decoder::Unorm(decode_bc4),
+        Format::Bc4RSnorm => Decoder::Float(decode_bc4_snorm),
+        Format::Bc5RgUnorm => Decoder::Unorm(decode_bc5),
+        Format::Bc5RgSnorm => Decoder::Float(decode_bc5_snorm),
+        Format::Bc6hRgbUfloat => Decoder::Float(|block| decode_bc6h(block, false)),
+        Format::Bc6hRgbFloat => Decoder::Float(|block| decode_bc6h(block, true)),
+        Format::Bc7RgbaUnorm | Format::Bc7RgbaUnormSrgb => Decoder::Unorm(decode_bc7),
+        Format::Etc2Rgb8Unorm | Format::Etc2Rgb8UnormSrgb => {
+            Decoder::Unorm(|block| decode_etc2(block, false))
+        }
+        Format::Etc2Rgb8A1Unorm | Format::Etc2Rgb8A1UnormSrgb => {
+            Decoder::Unorm(|block| decode_etc2(block, true))
+        }
+        Format::Etc2Rgba8Unorm | Format::Etc2Rgba8UnormSrgb => Decoder::Unorm(decode_etc2_rgba),
+        Format::EacR11Unorm => Decoder::Unorm(decode_eac_r),
+        Format::EacR11Snorm => Decoder::Float(decode_eac_r_snorm),
+        Format::EacRg11Unorm => Decoder::Unorm(decode_eac_rg),
+        Format::EacRg11Snorm => Decoder::Float(decode_eac_rg_snorm),
+        _ => return None,
+    })
+}
+
+fn rgb565(color: u16) -> [u8; 4] {
+    let (r, g, b) = ((color >> 11) & 31, (color >> 5) & 63, color & 31);
+    [
+        (r << 3 | r >> 2) as u8,
+        (g << 2 | g >> 4) as u8,
+        (b << 3 | b >> 2) as u8,
+        255,
+    ]
+}
+
+fn mix(a: [u8; 4], b: [u8; 4], weight_a: u32, weight_b: u32) -> [u8; 4] {
+    std::array::from_fn(|channel| {
+        ((a[channel] as u32 * weight_a + b[channel] as u32 * weight_b) / (weight_a + weight_b))
+            as u8
+    })
+}
+
+/// BC1 color block. BC2 and BC3 always use four colors, BC1 has three and transparent black
+/// when the first endpoint isn't greater than the second.
+fn decode_bc1(block: &[u8], punchthrough: bool) -> Block {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+    let palette = if c0 > c1 || !punchthrough {
+        [e0, e1, mix(e0, e1, 2, 1), mix(e0, e1, 1, 2)]
+    } else {
+        [e0, e1, mix(e0, e1, 1, 1), [0; 4]]
+    };
+    let indices = u32::from_le_bytes(block[4..8].try_into().unwrap());
+    std::array::from_fn(|texel| palette[(indices >> (texel * 2)) as usize & 3])
+}
+
+fn decode_bc2(block: &[u8]) -> Block {
+    let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+    let mut colors = decode_bc1(&block[8..], false);
+    for (texel, color) in colors.iter_mut().enumerate() {
+        color[3] = ((alpha >> (texel * 4)) & 15) as u8 * 17;
+    }
+    colors
+}
+
+fn decode_bc3(block: &[u8]) -> Block {
+    let alpha = decode_bc4_channel(&block[..8]);
+    let mut colors = decode_bc1(&block[8..], false);
+    for (color, alpha) in colors.iter_mut().zip(alpha) {
+        color[3] = alpha;
+    }
+    colors
+}
+
+fn decode_bc4_channel(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let palette: [u8; 8] = std::array::from_fn(|index| {
+        let index = index as u32;
+        (match index {
+            0 => a0,
+            1 => a1,
+            _ if a0 > a1 => ((8 - index) * a0 + (index - 1) * a1) / 7,
+            6 => 0,
+            7 => 255,
+            _ => ((6 - index) * a0 + (index - 1) * a1) / 5,
+        }) as u8
+    });
+    let mut bytes = [0; 8];
+    bytes[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bytes);
+    std::array::from_fn(|texel| palette[(indices >> (texel * 3)) as usize & 7])
+}
+
+fn decode_bc4(block: &[u8]) -> Block {
+    decode_bc4_channel(block).map(|red| [red, 0, 0, 255])
+}
+
+fn decode_bc5(block: &[u8]) -> Block {
+    let red = decode_bc4_channel(&block[..8]);
+    let green = decode_bc4_channel(&block[8..]);
+    std::array::from_fn(|texel| [red[texel], green[texel], 0, 255])
+}
+
+/// Signed BC4 channel in `-1..=1`, -128 reading as -127.
+fn decode_bc4_snorm_channel(block: &[u8]) -> [f32; 16] {
+    let (a0, a1) = (block[0] as i8, block[1] as i8);
+    let (e0, e1) = (a0.max(-127) as f32 / 127.0, a1.max(-127) as f32 / 127.0);
+    let palette: [f32; 8] = std::array::from_fn(|index| {
+        let index = index as f32;
+        match index as u32 {
+            0 => e0,
+            1 => e1,
+            _ if a0 > a1 => ((8.0 - index) * e0 + (index - 1.0) * e1) / 7.0,
+            6 => -1.0,
+            7 => 1.0,
+            _ => ((6.0 - index) * e0 + (index - 1.0) * e1) / 5.0,
+        }
+    });
+    let mut bytes = [0; 8];
+    bytes[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bytes);
+    std::array::from_fn(|texel| palette[(indices >> (texel * 3)) as usize & 7])
+}
+
+fn decode_bc4_snorm(block: &[u8]) -> FloatBlock {
+    decode_bc4_snorm_channel(block).map(|red| [red, 0.0, 0.0, 1.0])
+}
+
+fn decode_bc5_snorm(block: &[u8]) -> FloatBlock {
+    let red = decode_bc4_snorm_channel(&block[..8]);
+    let green = decode_bc4_snorm_channel(&block[8..]);
+    std::array::from_fn(|texel| [red[texel], green[texel], 0.0, 1.0])
+}
+
+/// Per mode: subsets, partition bits, rotation bits, index selection bits, color bits, alpha
+/// bits, whether each endpoint has a P-bit, whether each subset shares one, index bits and
+/// secondary index bits.
+const BC7_MODES: [[u32; 10]; 8] = [
+    [3, 4, 0, 0, 4, 0, 1, 0, 3, 0],
+    [2, 6, 0, 0, 6, 0, 0, 1, 3, 0],
+    [3, 6, 0, 0, 5, 0, 0, 0, 2, 0],
+    [2, 6, 0, 0, 7, 0, 1, 0, 2, 0],
+    [1, 0, 2, 1, 5, 6, 0, 0, 2, 3],
+    [1, 0, 2, 0, 7, 8, 0, 0, 2, 2],
+    [1, 0, 0, 0, 7, 7, 1, 0, 4, 0],
+    [2, 6, 0, 0, 5, 5, 1, 0, 2, 0],
+];
+
+const PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80, 0xc800, 0xffec, 0xfe80, 0xe800,
+    0xffe8, 0xff00, 0xfff0, 0xf000, 0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c, 0xaaaa, 0xf0f0, 0x5a5a, 0x33cc,
+    0x3c3c, 0x55aa, 0x9696, 0xa55a, 0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c, 0x9336, 0x9cc6, 0x817e, 0xe718,
+    0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+const PARTITIONS_3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2],
+    [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+    [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0],
+    [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0],
+    [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+    [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2],
+    [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0],
+    [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+    [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0],
+    [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1],
+    [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1],
+    [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+    [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2],
+    [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2],
+    [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+    [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1],
+    [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+const ANCHORS_3_1: [u8; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3, 3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5,
+    15, 15, 8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15, 3, 15, 5, 5, 5, 8, 5, 10, 5,
+    10, 8, 13, 15, 12, 3, 3,
+];
+
+const ANCHORS_3_2: [u8; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8, 15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6,
+    10, 15, 15, 10, 8, 15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8, 15, 3, 15, 15, 15,
+    15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Reads a block from its least significant bit up.
+struct Bits(u128);
+
+impl Bits {
+    fn read(&mut self, count: u32) -> u32 {
+        let value = (self.0 & ((1 << count) - 1)) as u32;
+        self.0 >>= count;
+        value
+    }
+}
+
+fn interpolate(e0: u32, e1: u32, index: u32, index_bits: u32) -> u8 {
+    let weight = match index_bits {
+        2 => WEIGHTS_2[index as usize],
+        3 => WEIGHTS_3[index as usize],
+        _ => WEIGHTS_4[index as usize],
+    };
+    (((64 - weight) * e0 + weight * e1 + 32) >> 6) as u8
+}
+
+fn decode_bc7(block: &[u8]) -> Block {
+    let mut bits = Bits(u128::from_le_bytes(block.try_into().unwrap()));
+    let Some(mode) = (0..8).find(|_| bits.read(1) == 1) else {
+        return [[0; 4]; 16];
+    };
+    let [subsets, partition_bits, rotation_bits, selection_bits, color_bits, alpha_bits, endpoint_pbits, shared_pbits, index_bits, secondary_index_bits] =
+        BC7_MODES[mode];
+    let partition = bits.read(partition_bits) as usize;
+    let rotation = bits.read(rotation_bits);
+    let selection = bits.read(selection_bits);
+    let endpoint_count = subsets as usize * 2;
+    let mut endpoints = [[255; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[channel] = bits.read(color_bits);
+        }
+    }
+    if alpha_bits > 0 {
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[3] = bits.read(alpha_bits);
+        }
+    }
+    let channels = if alpha_bits > 0 { 4 } else { 3 };
+    let mut pbits = [0; 6];
+    if endpoint_pbits == 1 {
+        for pbit in &mut pbits[..endpoint_count] {
+            *pbit = bits.read(1);
+        }
+    } else if shared_pbits == 1 {
+        for subset in 0..subsets as usize {
+            let pbit = bits.read(1);
+            pbits[subset * 2] = pbit;
+            pbits[subset * 2 + 1] = pbit;
+        }
+    }
+    let has_pbits = endpoint_pbits | shared_pbits;
+    for (endpoint, pbit) in endpoints[..endpoint_count].iter_mut().zip(pbits) {
+        for (channel, value) in endpoint[..channels].iter_mut().enumerate() {
+            let precision = if channel < 3 { color_bits } else { alpha_bits } + has_pbits;
+            let extended = (*value << has_pbits | pbit) << (8 - precision);
+            *value = extended | extended >> precision;
+        }
+    }
+    let subset = |texel: usize| match subsets {
+        1 => 0,
+        2 => (PARTITIONS_2[partition] >> texel & 1) as usize,
+        _ => PARTITIONS_3[partition][texel] as usize,
+    };
+    let is_anchor = |texel: usize| {
+        texel == 0
+            || match subsets {
+                2 => texel == ANCHORS_2[partition] as usize,
+                3 => {
+                    texel == ANCHORS_3_1[partition] as usize
+                        || texel == ANCHORS_3_2[partition] as usize
+                }
+                _ => false,
+            }
+    };
+    let indices: [u32; 16] =
+        std::array::from_fn(|texel| bits.read(index_bits - is_anchor(texel) as u32));
+    let secondary_indices: [u32; 16] = std::array::from_fn(|texel| {
+        if secondary_index_bits > 0 {
+            bits.read(secondary_index_bits - (texel == 0) as u32)
+        } else {
+            0
+        }
+    });
+    std::array::from_fn(|texel| {
+        let subset = subset(texel);
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        let primary = (indices[texel], index_bits);
+        let secondary = (secondary_indices[texel], secondary_index_bits);
+        let (color_index, alpha_index) = match (secondary_index_bits, selection) {
+            (0, _) => (primary, primary),
+            (_, 0) => (primary, secondary),
+            _ => (secondary, primary),
+        };
+        let mut color: [u8; 4] = std::array::from_fn(|channel| {
+            let (index, index_bits) = if channel < 3 {
+                color_index
+            } else {
+                alpha_index
+            };
+            interpolate(e0[channel], e1[channel], index, index_bits)
+        });
+        if rotation > 0 {
+            color.swap(3, rotation as usize - 1);
+        }
+        color
+    })
+}
+
+// BC6H endpoint fields, named as in the specification: `w` and `x` are the endpoints of the
+// first subset, `y` and `z` those of the second, `D` is the partition.
+const RW: u8 = 0;
+const GW: u8 = 1;
+const BW: u8 = 2;
+const RX: u8 = 3;
+const GX: u8 = 4;
+const BX: u8 = 5;
+const RY: u8 = 6;
+const GY: u8 = 7;
+const BY: u8 = 8;
+const RZ: u8 = 9;
+const GZ: u8 = 10;
+const BZ: u8 = 11;
+const D: u8 = 12;
+
+struct Bc6hMode {
+    /// Mode bits, two for the first two modes and five for the others.
+    bits: u32,
+    subsets: usize,
+    endpoint_bits: u32,
+    /// Bits of the other endpoints per channel, deltas from `w` when `transformed`.
+    delta_bits: [u32; 3],
+    transformed: bool,
+    /// Field, lowest bit and bit count of every run of bits after the mode bits.
+    layout: &'static [(u8, u8, u8)],
+}
+
+#[rustfmt::skip]
+const BC6H_MODES: [Bc6hMode; 14] = [
+    Bc6hMode { bits: 0x00, subsets: 2, endpoint_bits: 10, delta_bits: [5, 5, 5], transformed: true, layout: &[
+        (GY, 4, 1), (BY, 4, 1), (BZ, 4, 1), (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5),
+        (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1),
+        (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5),
+    ] },
+    Bc6hMode { bits: 0x01, subsets: 2, endpoint_bits: 7, delta_bits: [6, 6, 6], transformed: true, layout: &[
+        (GY, 5, 1), (GZ, 4, 1), (GZ, 5, 1), (RW, 0, 7), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1),
+        (GW, 0, 7), (BY, 5, 1), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 7), (BZ, 3, 1), (BZ, 5, 1),
+        (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4),
+        (RY, 0, 6), (RZ, 0, 6), (D, 0, 5),
+    ] },
+    Bc6hMode { bits: 0x02, subsets: 2, endpoint_bits: 11, delta_bits: [5, 4, 4], transformed: true, layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (RW, 10, 1), (GY, 0, 4), (GX, 0, 4),
+        (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4),
+        (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5),
+    ] },
+    Bc6hMode { bits: 0x06, subsets: 2, endpoint_bits: 11, delta_bits: [4, 5, 4], transformed: true, layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (GZ, 4, 1), (GY, 0, 4),
+        (GX, 0, 5), (GW, 10, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4),
+        (RY, 0, 4), (BZ, 0, 1), (BZ, 2, 1), (RZ, 0, 4), (GY, 4, 1), (BZ, 3, 1), (D, 0, 5),
+    ] },
+    Bc6hMode { bits: 0x0a, subsets: 2, endpoint_bits: 11, delta_bits: [4, 4, 5], transformed: true, layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (BY, 4, 1), (GY, 0, 4),
+        (GX, 0, 4), (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BW, 10, 1), (BY, 0, 4),
+        (RY, 0, 4), (BZ, 1, 1), (BZ, 2, 1), (RZ, 0, 4), (BZ, 4, 1), (BZ, 3, 1), (D, 0, 5),
+    ] },
+    Bc6hMode { bits: 0x0e, subsets: 2, endpoint_bits: 9, delta_bits: [5, 5, 5], transformed: true, layout: &[
+        (RW, 0, 9), (BY, 4, 1), (GW, 0, 9), (GY, 4, 1), (BW, 0, 9), (BZ, 4, 1), (RX, 0, 5),
+        (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1),
+        (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5),
+    ] },
+    Bc6hMode { bits: 0x12, subsets: 2, endpoint_bits: 8, delta_bits: [6, 5, 5], transformed: true, layout: &[
+        (RW, 0, 8), (GZ, 4, 1), (BY, 4, 1), (GW, 0, 8), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 8),
+        (BZ, 3, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4),
+        (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6), (D, 0, 5),
+    ] },
+    Bc6hMode { bits: 0x16, subsets: 2, endpoint_bits: 8, delta_bits: [5, 6, 5], transformed: true, layout: &[
+        (RW, 0, 8), (BZ, 0, 1), (BY, 4, 1), (GW, 0, 8), (GY, 5, 1), (GY, 4, 1), (BW, 0, 8),
+        (GZ, 5, 1), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4),
+        (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        (D, 0, 5),
+    ] },
+    Bc6hMode { bits: 0x1a, subsets: 2, endpoint_bits: 8, delta_bits: [5, 5, 6], transformed: true, layout: &[
+        (RW, 0, 8), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 8), (BY, 5, 1), (GY, 4, 1), (BW, 0, 8),
+        (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1),
+        (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        (D, 0, 5),
+    ] },
+    Bc6hMode { bits: 0x1e, subsets: 2, endpoint_bits: 6, delta_bits: [6, 6, 6], transformed: false, layout: &[
+        (RW, 0, 6), (GZ, 4, 1), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 6), (GY, 5, 1),
+        (BY, 5, 1), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 6), (GZ, 5, 1), (BZ, 3, 1), (BZ, 5, 1),
+        (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4),
+        (RY, 0, 6), (RZ, 0, 6), (D, 0, 5),
+    ] },
+    Bc6hMode { bits: 0x03, subsets: 1, endpoint_bits: 10, delta_bits: [10, 10, 10], transformed: false, layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 10), (GX, 0, 10), (BX, 0, 10),
+    ] },
+    Bc6hMode { bits: 0x07, subsets: 1, endpoint_bits: 11, delta_bits: [9, 9, 9], transformed: true, layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 9), (RW, 10, 1), (GX, 0, 9), (GW, 10, 1),
+        (BX, 0, 9), (BW, 10, 1),
+    ] },
+    // The high bits of `w` are stored in reverse order in the last two modes.
+    Bc6hMode { bits: 0x0b, subsets: 1, endpoint_bits: 12, delta_bits: [8, 8, 8], transformed: true, layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 8), (RW, 11, 1), (RW, 10, 1), (GX, 0, 8),
+        (GW, 11, 1), (GW, 10, 1), (BX, 0, 8), (BW, 11, 1), (BW, 10, 1),
+    ] },
+    Bc6hMode { bits: 0x0f, subsets: 1, endpoint_bits: 16, delta_bits: [4, 4, 4], transformed: true, layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 15, 1), (RW, 14, 1), (RW, 13, 1),
+        (RW, 12, 1), (RW, 11, 1), (RW, 10, 1), (GX, 0, 4), (GW, 15, 1), (GW, 14, 1), (GW, 13, 1),
+        (GW, 12, 1), (GW, 11, 1), (GW, 10, 1), (BX, 0, 4), (BW, 15, 1), (BW, 14, 1), (BW, 13, 1),
+        (BW, 12, 1), (BW, 11, 1), (BW, 10, 1),
+    ] },
+];
+
+fn sign_extend(value: u32, bits: u32) -> i32 {
+    ((value << (32 - bits)) as i32) >> (32 - bits)
+}
+
+/// Scales an endpoint to 16 bits, or 15 bits and a sign.
+fn unquantize_bc6h(value: i32, bits: u32, signed: bool) -> i32 {
+    if !signed {
+        match value {
+            _ if bits >= 15 => value,
+            0 => 0,
+            _ if value == (1 << bits) - 1 => 0xffff,
+            _ => ((value << 16) + 0x8000) >> bits,
+        }
+    } else {
+        let magnitude = match value.abs() {
+            _ if bits >= 16 => return value,
+            0 => 0,
+            magnitude if magnitude >= (1 << (bits - 1)) - 1 => 0x7fff,
+            magnitude => ((magnitude << 15) + 0x4000) >> (bits - 1),
+        };
+        if value < 0 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+/// BC6H block, `signed` for the signed float variant. Reserved modes decode to black.
+fn decode_bc6h(block: &[u8], signed: bool) -> FloatBlock {
+    let mut bits = Bits(u128::from_le_bytes(block.try_into().unwrap()));
+    let mut mode_bits = bits.read(2);
+    if mode_bits > 1 {
+        mode_bits |= bits.read(3) << 2;
+    }
+    let Some(mode) = BC6H_MODES.iter().find(|mode| mode.bits == mode_bits) else {
+        return [[0.0, 0.0, 0.0, 1.0]; 16];
+    };
+    let mut fields = [0; 13];
+    for &(field, low, count) in mode.layout {
+        fields[field as usize] |= bits.read(count as u32) << low;
+    }
+    let endpoint_bits = mode.endpoint_bits;
+    let extend = |value: u32| {
+        if signed {
+            sign_extend(value, endpoint_bits)
+        } else {
+            value as i32
+        }
+    };
+    let mut endpoints = [[0; 3]; 4];
+    for endpoint in 0..mode.subsets * 2 {
+        for channel in 0..3 {
+            let value = fields[endpoint * 3 + channel];
+            endpoints[endpoint][channel] = if endpoint > 0 && mode.transformed {
+                let delta = sign_extend(value, mode.delta_bits[channel]);
+                let mask = (1 << endpoint_bits) - 1;
+                extend((endpoints[0][channel] + delta) as u32 & mask)
+            } else {
+                extend(value)
+            };
+        }
+    }
+    let endpoints = endpoints
+        .map(|endpoint| endpoint.map(|value| unquantize_bc6h(value, endpoint_bits, signed)));
+    let partition = fields[D as usize] as usize;
+    let index_bits = if mode.subsets == 2 { 3 } else { 4 };
+    let indices: [u32; 16] = std::array::from_fn(|texel| {
+        let anchor = texel == 0 || (mode.subsets == 2 && texel == ANCHORS_2[partition] as usize);
+        bits.read(index_bits - anchor as u32)
+    });
+    std::array::from_fn(|texel| {
+        let subset = if mode.subsets == 2 {
+            (PARTITIONS_2[partition] >> texel & 1) as usize
+        } else {
+            0
+        };
+        let weight = match index_bits {
+            3 => WEIGHTS_3[indices[texel] as usize],
+            _ => WEIGHTS_4[indices[texel] as usize],
+        } as i32;
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        let [r, g, b] = std::array::from_fn(|channel| {
+            let value = (e0[channel] * (64 - weight) + e1[channel] * weight + 32) >> 6;
+            // Scales the interpolated value to the largest finite half float.
+            let half = if !signed {
+                (value * 31) >> 6
+            } else if value < 0 {
+                0x8000 | ((-value * 31) >> 5)
+            } else {
+                (value * 31) >> 5
+            };
+            f16_to_f32(half as u16)
+        });
+        [r, g, b, 1.0]
+    })
+}
+
+const ETC_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/// Bits `high` down to `low` of a big endian ETC block.
+fn field(block: u64, high: u32, low: u32) -> i32 {
+    ((block >> low) & ((1 << (high - low + 1)) - 1)) as i32
+}
+
+fn offset(color: [i32; 3], offset: i32) -> [u8; 4] {
+    let [r, g, b] = color.map(|channel| (channel + offset).clamp(0, 255) as u8);
+    [r, g, b, 255]
+}
+
+/// ETC2 RGB block, covering the ETC1 individual and differential modes and the T, H and planar
+/// modes hidden in differential overflows. With `punchthrough` the differential bit instead
+/// tells whether the block is opaque.
+fn decode_etc2(block: &[u8], punchthrough: bool) -> Block {
+    let block = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let flag = field(block, 33, 33) == 1;
+    let opaque = !punchthrough || flag;
+    let differential = punchthrough || flag;
+    // Texels are stored column by column, two bits split between both halves of the low word.
+    let index = |texel: usize| {
+        let position = (texel % 4 * 4 + texel / 4) as u32;
+        (field(block, 16 + position, 16 + position) << 1 | field(block, position, position))
+            as usize
+    };
+    let extend4 = |value: i32| value * 17;
+    let extend5 = |value: i32| value << 3 | value >> 2;
+    let sign3 = |value: i32| (value << 29) >> 29;
+    let paint = |colors: [[u8; 4]; 4]| -> Block {
+        std::array::from_fn(|texel| match index(texel) {
+            2 if !opaque => [0; 4],
+            index => colors[index],
+        })
+    };
+    let (base1, base2) = if !differential {
+        (
+            [
+                field(block, 63, 60),
+                field(block, 55, 52),
+                field(block, 47, 44),
+            ]
+            .map(extend4),
+            [
+                field(block, 59, 56),
+                field(block, 51, 48),
+                field(block, 43, 40),
+            ]
+            .map(extend4),
+        )
+    } else {
+        let base = [
+            field(block, 63, 59),
+            field(block, 55, 51),
+            field(block, 47, 43),
+        ];
+        let delta = [
+            field(block, 58, 56),
+            field(block, 50, 48),
+            field(block, 42, 40),
+        ]
+        .map(sign3);
+        let second: [i32; 3] = std::array::from_fn(|channel| base[channel] + delta[channel]);
+        if !(0..32).contains(&second[0]) {
+            let c1 = [
+                field(block, 60, 59) << 2 | field(block, 57, 56),
+                field(block, 55, 52),
+                field(block, 51, 48),
+            ]
+            .map(extend4);
+            let c2 = [
+                field(block, 47, 44),
+                field(block, 43, 40),
+                field(block, 39, 36),
+            ]
+            .map(extend4);
+            let distance =
+                ETC_DISTANCES[(field(block, 35, 34) << 1 | field(block, 32, 32)) as usize];
+            return paint([
+                offset(c1, 0),
+                offset(c2, distance),
+                offset(c2, 0),
+                offset(c2, -distance),
+            ]);
+        }
+        if !(0..32).contains(&second[1]) {
+            let c1 = [
+                field(block, 62, 59),
+                field(block, 58, 56) << 1 | field(block, 52, 52),
+                field(block, 51, 51) << 3 | field(block, 49, 47),
+            ];
+            let c2 = [
+                field(block, 46, 43),
+                field(block, 42, 39),
+                field(block, 38, 35),
+            ];
+            let order = (c1[0] << 8 | c1[1] << 4 | c1[2]) >= (c2[0] << 8 | c2[1] << 4 | c2[2]);
+            let distance = ETC_DISTANCES
+                [(field(block, 34, 34) << 2 | field(block, 32, 32) << 1 | order as i32) as usize];
+            let (c1, c2) = (c1.map(extend4), c2.map(extend4));
+            return paint([
+                offset(c1, distance),
+                offset(c1, -distance),
+                offset(c2, distance),
+                offset(c2, -distance),
+            ]);
+        }
+        if !(0..32).contains(&second[2]) {
+            let extend6 = |value: i32| value << 2 | value >> 4;
+            let extend7 = |value: i32| value << 1 | value >> 6;
+            let origin = [
+                extend6(field(block, 62, 57)),
+                extend7(field(block, 56, 56) << 6 | field(block, 54, 49)),
+                extend6(
+                    field(block, 48, 48) << 5 | field(block, 44, 43) << 3 | field(block, 41, 39),
+                ),
+            ];
+            let horizontal = [
+                extend6(field(block, 38, 34) << 1 | field(block, 32, 32)),
+                extend7(field(block, 31, 25)),
+                extend6(field(block, 24, 19)),
+            ];
+            let vertical = [
+                extend6(field(block, 18, 13)),
+                extend7(field(block, 12, 6)),
+                extend6(field(block, 5, 0)),
+            ];
+            return std::array::from_fn(|texel| {
+                let (x, y) = ((texel % 4) as i32, (texel / 4) as i32);
+                let [r, g, b] = std::array::from_fn(|channel| {
+                    let o = origin[channel];
+                    ((x * (horizontal[channel] - o) + y * (vertical[channel] - o) + 4 * o + 2) >> 2)
+                        .clamp(0, 255) as u8
+                });
+                [r, g, b, 255]
+            });
+        }
+        (base.map(extend5), second.map(extend5))
+    };
+    let tables = [field(block, 39, 37), field(block, 36, 34)];
+    let flip = field(block, 32, 32) == 1;
+    std::array::from_fn(|texel| {
+        let (x, y) = (texel % 4, texel / 4);
+        let second = if flip { y >= 2 } else { x >= 2 };
+        let (base, [small, large]) = if second {
+            (base2, ETC_MODIFIERS[tables[1] as usize])
+        } else {
+            (base1, ETC_MODIFIERS[tables[0] as usize])
+        };
+        match index(texel) {
+            0 if !opaque => offset(base, 0),
+            0 => offset(base, small),
+            1 => offset(base, large),
+            2 if !opaque => [0; 4],
+            2 => offset(base, -small),
+            _ => offset(base, -large),
+        }
+    })
+}
+
+/// Base codeword, multiplier and per texel modifier of an EAC block.
+fn eac_fields(block: &[u8]) -> (i32, i32, [i32; 16]) {
+    let block = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let modifiers = EAC_MODIFIERS[field(block, 51, 48) as usize];
+    let texels = std::array::from_fn(|texel| {
+        let position = (texel % 4 * 4 + texel / 4) as u32;
+        let high = 47 - position * 3;
+        modifiers[field(block, high, high - 2) as usize]
+    });
+    (field(block, 63, 56), field(block, 55, 52), texels)
+}
+
+/// EAC channel in 8 bits. `eleven` decodes the 11 bit R11 and RG11 variant and rounds it down
+/// to 8 bits, otherwise it's the alpha of ETC2 RGBA8.
+fn decode_eac(block: &[u8], eleven: bool) -> [u8; 16] {
+    let (base, multiplier, modifiers) = eac_fields(block);
+    modifiers.map(|modifier| {
+        if eleven {
+            let scale = if multiplier == 0 { 1 } else { multiplier * 8 };
+            let value = (base * 8 + 4 + modifier * scale).clamp(0, 2047);
+            ((value * 255 + 1023) / 2047) as u8
+        } else {
+            (base + modifier * multiplier).clamp(0, 255) as u8
+        }
+    })
+}
+
+/// Signed 11 bit EAC channel in `-1..=1`, a base of -128 reading as -127.
+fn decode_eac_snorm(block: &[u8]) -> [f32; 16] {
+    let (base, multiplier, modifiers) = eac_fields(block);
+    let base = (base as u8 as i8).max(-127) as i32;
+    modifiers.map(|modifier| {
+        let scale = if multiplier == 0 { 1 } else { multiplier * 8 };
+        (base * 8 + modifier * scale).clamp(-1023, 1023) as f32 / 1023.0
+    })
+}
+
+fn decode_etc2_rgba(block: &[u8]) -> Block {
+    let alpha = decode_eac(&block[..8], false);
+    let mut colors = decode_etc2(&block[8..], false);
+    for (color, alpha) in colors.iter_mut().zip(alpha) {
+        color[3] = alpha;
+    }
+    colors
+}
+
+fn decode_eac_r(block: &[u8]) -> Block {
+    decode_eac(block, true).map(|red| [red, 0, 0, 255])
+}
+
+fn decode_eac_rg(block: &[u8]) -> Block {
+    let red = decode_eac(&block[..8], true);
+    let green = decode_eac(&block[8..], true);
+    std::array::from_fn(|texel| [red[texel], green[texel], 0, 255])
+}
+
+fn decode_eac_r_snorm(block: &[u8]) -> FloatBlock {
+    decode_eac_snorm(block).map(|red| [red, 0.0, 0.0, 1.0])
+}
+
+fn decode_eac_rg_snorm(block: &[u8]) -> FloatBlock {
+    let red = decode_eac_snorm(&block[..8]);
+    let green = decode_eac_snorm(&block[8..]);
+    std::array::from_fn(|texel| [red[texel], green[texel], 0.0, 1.0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packs `(value, bits)` fields from the least significant bit up, as BC6H and BC7 read them.
+    fn pack(fields: &[(u32, u32)]) -> [u8; 16] {
+        let (value, length) = fields
+            .iter()
+            .fold((0u128, 0), |(value, length), &(field, bits)| {
+                (value | (field as u128) << length, length + bits)
+            });
+        assert_eq!(length, 128);
+        value.to_le_bytes()
+    }
+
+    /// Index fields of a block, one bit shorter at the anchors, `set` overriding the zeros.
+    fn indices(bits: u32, anchors: &[usize], set: &[(usize, u32)]) -> Vec<(u32, u32)> {
+        (0..16)
+            .map(|texel| {
+                let value = set
+                    .iter()
+                    .find(|&&(index, _)| index == texel)
+                    .map_or(0, |&(_, value)| value);
+                (value, bits - anchors.contains(&texel) as u32)
+            })
+            .collect()
+    }
+
+    /// Channel fields of every endpoint, red of all endpoints first.
+    fn endpoints(endpoints: &[[u32; 3]], bits: u32) -> Vec<(u32, u32)> {
+        (0..3)
+            .flat_map(|channel| {
+                endpoints
+                    .iter()
+                    .map(move |endpoint| (endpoint[channel], bits))
+            })
+            .collect()
+    }
+
+    /// Big endian ETC block from `(value, high, low)` fields.
+    fn etc(fields: &[(u64, u32, u32)]) -> [u8; 8] {
+        fields
+            .iter()
+            .fold(0u64, |block, &(value, high, low)| {
+                assert!(value < 1 << (high - low + 1));
+                block | value << low
+            })
+            .to_be_bytes()
+    }
+
+    /// ETC index fields giving texels 0 to 3 of the first row the indices 0 to 3.
+    const ETC_ROW_INDICES: [(u64, u32, u32); 4] =
+        [(1, 4, 4), (1, 24, 24), (1, 28, 28), (1, 12, 12)];
+
+    #[test]
+    fn bc1() {
+        let block = [0x00, 0xf8, 0x1f, 0x00, 0b11100100, 0, 0, 0];
+        let colors = decode_bc1(&block, true);
+        assert_eq!(colors[0], [255, 0, 0, 255]);
+        assert_eq!(colors[1], [0, 0, 255, 255]);
+        assert_eq!(colors[2], [170, 0, 85, 255]);
+        assert_eq!(colors[3], [85, 0, 170, 255]);
+        let block = [0x1f, 0x00, 0x00, 0xf8, 0b11100100, 0, 0, 0];
+        let colors = decode_bc1(&block, true);
+        assert_eq!(colors[2], [127, 0, 127, 255]);
+        assert_eq!(colors[3], [0; 4]);
+    }
+
+    #[test]
+    fn bc4_snorm() {
+        let colors = decode_bc4_snorm_channel(&[0x7f, 0x81, 0b10001000, 0, 0, 0, 0, 0]);
+        assert_eq!(colors[0], 1.0);
+        assert_eq!(colors[1], -1.0);
+        assert_eq!(colors[2], 5.0 / 7.0);
+        let colors = decode_bc4_snorm_channel(&[0x80, 0x40, 0b10111000, 0b101, 0, 0, 0, 0]);
+        assert_eq!(colors[0], -1.0);
+        assert_eq!(colors[1], 1.0);
+        assert_eq!(colors[2], -1.0);
+        assert_eq!(colors[3], (-4.0 + 64.0 / 127.0) / 5.0);
+    }
+
+    #[test]
+    fn bc6h_untransformed() {
+        let mut fields = vec![(0x03, 5)];
+        fields.extend([(1023, 10), (0, 10), (512, 10), (0, 10), (1023, 10), (0, 10)]);
+        fields.extend(indices(4, &[0], &[(1, 15)]));
+        let colors = decode_bc6h(&pack(&fields), false);
+        assert_eq!(colors[0], [65504.0, 0.0, 1.5146484, 1.0]);
+        assert_eq!(colors[1], [0.0, 65504.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn bc6h_signed() {
+        let mut fields = vec![(0x03, 5)];
+        fields.extend([(0x200, 10), (1, 10), (0, 10), (0, 10), (0, 10), (0, 10)]);
+        fields.extend(indices(4, &[0], &[]));
+        let colors = decode_bc6h(&pack(&fields), true);
+        assert_eq!(colors[0], [-65504.0, f16_to_f32(93), 0.0, 1.0]);
+    }
+
+    #[test]
+    fn bc6h_transformed() {
+        // Mode 14, 16 bit endpoints with the high bits of `w` reversed and 4 bit deltas.
+        let reversed = |value: u32| (0..6).map(move |bit| (value >> (15 - bit) & 1, 1));
+        let mut fields = vec![(0x0f, 5), (0x234, 10), (0, 10), (0x3ff, 10), (0b1111, 4)];
+        fields.extend(reversed(0x1234));
+        fields.push((0, 4));
+        fields.extend(reversed(0));
+        fields.push((0b0111, 4));
+        fields.extend(reversed(0xffff));
+        fields.extend(indices(4, &[0], &[(1, 15)]));
+        let colors = decode_bc6h(&pack(&fields), false);
+        assert_eq!(colors[0], [f16_to_f32(2257), 0.0, 65504.0, 1.0]);
+        assert_eq!(colors[1], [f16_to_f32(2256), 0.0, f16_to_f32(2), 1.0]);
+    }
+
+    #[test]
+    fn bc6h_reserved_mode() {
+        let colors = decode_bc6h(&pack(&[(0x13, 5), (0, 123)]), false);
+        assert_eq!(colors, [[0.0, 0.0, 0.0, 1.0]; 16]);
+    }
+
+    #[test]
+    fn bc7_mode_0() {
+        let mut fields = vec![(1, 1), (0, 4)];
+        let colors = [
+            [15, 0, 0],
+            [0, 15, 0],
+            [0, 0, 15],
+            [0; 3],
+            [8, 8, 8],
+            [0; 3],
+        ];
+        fields.extend(endpoints(&colors, 4));
+        fields.extend([(1, 1), (0, 1), (0, 1), (0, 1), (0, 1), (0, 1)]);
+        fields.extend(indices(3, &[0, 3, 15], &[(1, 7)]));
+        let colors = decode_bc7(&pack(&fields));
+        assert_eq!(colors[0], [255, 8, 8, 255]);
+        assert_eq!(colors[1], [0, 247, 0, 255]);
+        assert_eq!(colors[2], [0, 0, 247, 255]);
+        assert_eq!(colors[9], [132, 132, 132, 255]);
+    }
+
+    #[test]
+    fn bc7_mode_1() {
+        let mut fields = vec![(0b10, 2), (0, 6)];
+        fields.extend(endpoints(&[[63, 0, 0], [0, 63, 0], [0, 0, 32], [0; 3]], 6));
+        fields.extend([(1, 1), (0, 1)]);
+        fields.extend(indices(3, &[0, 15], &[(1, 7)]));
+        let colors = decode_bc7(&pack(&fields));
+        assert_eq!(colors[0], [255, 2, 2, 255]);
+        assert_eq!(colors[1], [2, 255, 2, 255]);
+        assert_eq!(colors[2], [0, 0, 129, 255]);
+    }
+
+    #[test]
+    fn bc7_mode_2() {
+        let mut fields = vec![(0b100, 3), (0, 6)];
+        let colors = [
+            [31, 0, 0],
+            [0, 16, 0],
+            [0, 0, 31],
+            [0; 3],
+            [1, 1, 1],
+            [0; 3],
+        ];
+        fields.extend(endpoints(&colors, 5));
+        fields.extend(indices(2, &[0, 3, 15], &[(1, 3)]));
+        let colors = decode_bc7(&pack(&fields));
+        assert_eq!(colors[0], [255, 0, 0, 255]);
+        assert_eq!(colors[1], [0, 132, 0, 255]);
+        assert_eq!(colors[2], [0, 0, 255, 255]);
+        assert_eq!(colors[9], [8, 8, 8, 255]);
+    }
+
+    #[test]
+    fn bc7_mode_3() {
+        let mut fields = vec![(0b1000, 4), (0, 6)];
+        fields.extend(endpoints(
+            &[[127, 0, 0], [0, 64, 0], [0, 0, 100], [0; 3]],
+            7,
+        ));
+        fields.extend([(1, 1), (0, 1), (0, 1), (0, 1)]);
+        fields.extend(indices(2, &[0, 15], &[(1, 3)]));
+        let colors = decode_bc7(&pack(&fields));
+        assert_eq!(colors[0], [255, 1, 1, 255]);
+        assert_eq!(colors[1], [0, 128, 0, 255]);
+        assert_eq!(colors[2], [0, 0, 200, 255]);
+    }
+
+    #[test]
+    fn bc7_mode_4() {
+        // Rotation 3 swaps blue and alpha, index selection 1 colors with the 3 bit indices.
+        let mut fields = vec![(0b10000, 5), (3, 2), (1, 1)];
+        fields.extend(endpoints(&[[31, 0, 10], [0, 31, 0]], 5));
+        fields.extend([(0, 6), (63, 6)]);
+        fields.extend(indices(2, &[0], &[(1, 3)]));
+        fields.extend(indices(3, &[0], &[(2, 7)]));
+        let colors = decode_bc7(&pack(&fields));
+        assert_eq!(colors[0], [255, 0, 0, 82]);
+        assert_eq!(colors[1], [255, 0, 255, 82]);
+        assert_eq!(colors[2], [0, 255, 0, 0]);
+    }
+
+    #[test]
+    fn bc7_mode_5() {
+        let mut fields = vec![(0b100000, 6), (0, 2)];
+        fields.extend(endpoints(&[[127, 0, 0], [0, 64, 0]], 7));
+        fields.extend([(10, 8), (200, 8)]);
+        fields.extend(indices(2, &[0], &[(1, 3), (3, 1)]));
+        fields.extend(indices(2, &[0], &[(2, 3)]));
+        let colors = decode_bc7(&pack(&fields));
+        assert_eq!(colors[0], [255, 0, 0, 10]);
+        assert_eq!(colors[1], [0, 129, 0, 10]);
+        assert_eq!(colors[2], [255, 0, 0, 200]);
+        assert_eq!(colors[3], [171, 42, 0, 10]);
+    }
+
+    #[test]
+    fn bc7_mode_6() {
+        let mut fields = vec![(0b1000000, 7)];
+        fields.extend(endpoints(&[[64, 0, 0], [0, 127, 32]], 7));
+        fields.extend([(127, 7), (0, 7), (1, 1), (0, 1)]);
+        fields.extend(indices(4, &[0], &[(1, 15), (2, 8)]));
+        let colors = decode_bc7(&pack(&fields));
+        assert_eq!(colors[0], [129, 1, 1, 255]);
+        assert_eq!(colors[1], [0, 254, 64, 0]);
+        assert_eq!(colors[2], [60, 135, 34, 120]);
+    }
+
+    #[test]
+    fn bc7_mode_7() {
+        let mut fields = vec![(0b10000000, 8), (0, 6)];
+        fields.extend(endpoints(&[[31, 0, 0], [0, 31, 0], [0, 0, 16], [0; 3]], 5));
+        fields.extend([(31, 5), (0, 5), (16, 5), (0, 5)]);
+        fields.extend([(1, 1), (0, 1), (0, 1), (0, 1)]);
+        fields.extend(indices(2, &[0, 15], &[(1, 3)]));
+        let colors = decode_bc7(&pack(&fields));
+        assert_eq!(colors[0], [255, 4, 4, 255]);
+        assert_eq!(colors[1], [0, 251, 0, 0]);
+        assert_eq!(colors[2], [0, 0, 130, 130]);
+    }
+
+    #[test]
+    fn bc7_reserved_mode() {
+        assert_eq!(decode_bc7(&[0; 16]), [[0; 4]; 16]);
+    }
+
+    #[test]
+    fn etc2_t_mode() {
+        // Red of 30 plus a delta of 2 overflows.
+        let mut fields = vec![
+            (0b111, 63, 61),
+            (0b10, 60, 59),
+            (0b010, 58, 56),
+            (5, 55, 52),
+            (0, 51, 48),
+            (2, 47, 44),
+            (15, 43, 40),
+            (8, 39, 36),
+            (0b10, 35, 34),
+            (1, 33, 33),
+            (1, 32, 32),
+        ];
+        fields.extend(ETC_ROW_INDICES);
+        let colors = decode_etc2(&etc(&fields), false);
+        assert_eq!(colors[0], [170, 85, 0, 255]);
+        assert_eq!(colors[1], [66, 255, 168, 255]);
+        assert_eq!(colors[2], [34, 255, 136, 255]);
+        assert_eq!(colors[3], [2, 223, 104, 255]);
+    }
+
+    #[test]
+    fn etc2_h_mode() {
+        // Green of 0 plus a delta of -3 overflows.
+        let mut fields = vec![
+            (8, 62, 59),
+            (0b010, 58, 56),
+            (0, 55, 53),
+            (0, 52, 52),
+            (0, 51, 51),
+            (1, 50, 50),
+            (0b010, 49, 47),
+            (3, 46, 43),
+            (6, 42, 39),
+            (9, 38, 35),
+            (1, 34, 34),
+            (1, 33, 33),
+            (0, 32, 32),
+        ];
+        fields.extend(ETC_ROW_INDICES);
+        let colors = decode_etc2(&etc(&fields), false);
+        assert_eq!(colors[0], [168, 100, 66, 255]);
+        assert_eq!(colors[1], [104, 36, 2, 255]);
+        assert_eq!(colors[2], [83, 134, 185, 255]);
+        assert_eq!(colors[3], [19, 70, 121, 255]);
+    }
+
+    #[test]
+    fn etc2_planar_mode() {
+        // Blue of 2 plus a delta of -4 overflows.
+        let fields = [
+            (32, 62, 57),
+            (1, 56, 56),
+            (0b10, 44, 43),
+            (1, 42, 42),
+            (0b11111, 38, 34),
+            (1, 33, 33),
+            (1, 32, 32),
+            (127, 31, 25),
+            (63, 5, 0),
+        ];
+        let colors = decode_etc2(&etc(&fields), false);
+        assert_eq!(colors[0], [130, 129, 65, 255]);
+        assert_eq!(colors[3], [224, 224, 16, 255]);
+        assert_eq!(colors[12], [33, 32, 208, 255]);
+        assert_eq!(colors[15], [126, 127, 159, 255]);
+    }
+
+    #[test]
+    fn eac_snorm() {
+        let colors = decode_eac_snorm(&[0x80, 0x10, 0xe0, 0, 0, 0, 0, 0]);
+        assert_eq!(colors[0], -904.0 / 1023.0);
+        assert_eq!(colors[1], -1.0);
+        let colors = decode_eac_snorm(&[0x7f, 0x10, 0xe0, 0, 0, 0, 0, 0]);
+        assert_eq!(colors[0], 1.0);
+        assert_eq!(colors[1], 992.0 / 1023.0);
+    }
+
+    #[test]
+    fn invalid_files() {
+        assert!(matches!(
+            CompressedImage::from_ktx2(b"not a KTX2 file"),
+            Err(CompressedTextureError::Ktx2(_))
+        ));
+        assert!(matches!(
+            CompressedImage::from_dds(b"not a DDS file"),
+            Err(CompressedTextureError::Dds(_))
+        ));
+        let astc = CompressedImage {
+            format: Format::Astc {
+                block: AstcBlock::B4x4,
+                channel: AstcChannel::Unorm,
+            },
+            width: 4,
+            height: 4,
+            levels: vec![vec![0; 16]],
+        };
+        assert!(matches!(
+            astc.decompress(),
+            Err(CompressedTextureError::Undecodable(_))
+        ));
+    }
+
+    fn dds(mip_map_count: u32) -> Vec<u8> {
+        let mut dds = ddsfile::Dds::new_dxgi(ddsfile::NewDxgiParams {
+            height: 8,
+            width: 8,
+            depth: None,
+            format: ddsfile::DxgiFormat::BC1_UNorm,
+            mipmap_levels: Some(4),
+            array_layers: None,
+            caps2: None,
+            is_cubemap: false,
+            resource_dimension: ddsfile::D3D10ResourceDimension::Texture2D,
+            alpha_mode: ddsfile::AlphaMode::Unknown,
+        })
+        .unwrap();
+        dds.header.mip_map_count = Some(mip_map_count);
+        let mut data = Vec::new();
+        dds.write(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn dds_level_count() {
+        assert_eq!(CompressedImage::from_dds(&dds(0)).unwrap().levels.len(), 1);
+        assert_eq!(CompressedImage::from_dds(&dds(4)).unwrap().levels.len(), 4);
+        assert!(matches!(
+            CompressedImage::from_dds(&dds(5)),
+            Err(CompressedTextureError::TooManyLevels(5))
+        ));
+    }
+
+    #[test]
+    fn decompress_float() {
+        let image = CompressedImage {
+            format: Format::Bc4RSnorm,
+            width: 2,
+            height: 1,
+            levels: vec![vec![0x7f, 0x81, 0b1000, 0, 0, 0, 0, 0]],
+        };
+        let (format, levels) = image.decompress().unwrap();
+        assert_eq!(format, TextureFormat::Rgba16Float);
+        let halves = [1.0, 0.0, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0]
+            .map(|value| f32_to_f16(value).to_le_bytes())
+            .concat();
+        assert_eq!(levels, [halves]);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{Context, Shader};
+
+pub struct ComputePipelineBuilder<'a> {
+    shader: &'a Shader,
+    bind_group_layouts: Vec<wgpu::BindGroupLayout>,
+}
+
+impl<'a> ComputePipelineBuilder<'a> {
+    pub fn new(shader: &'a Shader) -> Self {
+        Self {
+            shader,
+            bind_group_layouts: Vec::new(),
+        }
+    }
+
+    pub(crate) fn with_bind_group_layout(&mut self, layout: wgpu::BindGroupLayout) {
+        self.bind_group_layouts.push(layout);
+    }
+
+    pub fn build(self, ctx: &Context) -> ComputePipeline {
+        let bind_group_layouts = self.bind_group_layouts.iter().collect::<Box<_>>();
+        let layout = ctx
+            .device()
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &bind_group_layouts,
+                push_constant_ranges: &[],
+            });
+        ComputePipeline(
+            ctx.device()
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: None,
+                    layout: Some(&layout),
+                    module: &self.shader.module,
+                    entry_point: "cs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+        )
+    }
+}
+
+pub struct ComputePipeline(wgpu::ComputePipeline);
+
+impl ComputePipeline {
+    pub fn attach<'a>(&'a self, pass: &mut ComputePass<'a>) {
+        pass.0.set_pipeline(&self.0);
+    }
+}
+
+/// Compute work recorded outside of a frame, e.g. to generate textures while loading.
+pub struct ComputeEncoder<'a> {
+    context: &'a Context,
+    encoder: wgpu::CommandEncoder,
+}
+
+impl<'a> ComputeEncoder<'a> {
+    pub fn new(ctx: &'a Context) -> Self {
+        Self {
+            context: ctx,
+            encoder: ctx
+                .device()
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default()),
+        }
+    }
+
+    pub fn pass(&mut self) -> ComputePass<'_> {
+        ComputePass::new(&mut self.encoder)
+    }
+
+    pub fn submit(self) {
+        self.context
+            .queue()
+            .submit(std::iter::once(self.encoder.finish()));
+    }
+}
+
+pub struct ComputePass<'a>(pub(crate) wgpu::ComputePass<'a>);
+
+impl<'a> ComputePass<'a> {
+    pub(crate) fn new(encoder: &'a mut wgpu::CommandEncoder) -> Self {
+        Self(encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default()))
+    }
+
+    pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        self.0.dispatch_workgroups(x, y, z);
+    }
+}
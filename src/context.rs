@@ -1,77 +1,500 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::Arc,
+};
 
 use glam::*;
+use image::{
+    error::{ParameterError, ParameterErrorKind},
+    ImageError, ImageResult,
+};
 use winit::{
-    dpi::PhysicalPosition,
-    keyboard::KeyCode,
-    window::{CursorGrabMode, Window},
+    dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
+    event::MouseButton,
+    event_loop::ActiveEventLoop,
+    keyboard::{KeyCode, ModifiersState},
+    window::{CursorGrabMode, CursorIcon, Fullscreen, Icon, Window, WindowId},
 };
 
-pub struct Context {
+use crate::{
+    mipmap::MipmapGenerator,
+    viewport::ViewportClear,
+    window_state::{AppWindow, InputMode, WindowState},
+    DepthFormat, Gesture, Input, State, Touch,
+};
+
+const SAMPLE_COUNT: u32 = 1;
+
+const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::PUSH_CONSTANTS
+    .union(wgpu::Features::MULTI_DRAW_INDIRECT)
+    .union(wgpu::Features::INDIRECT_FIRST_INSTANCE)
+    .union(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_BC)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ETC2)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ASTC)
+    .union(wgpu::Features::TEXTURE_COMPRESSION_ASTC_HDR)
+    .union(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+    .union(wgpu::Features::DEPTH32FLOAT_STENCIL8);
+
+/// GPU state shared by the contexts of every window, so resources can be used in all of them.
+pub(crate) struct Gpu {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    format: wgpu::TextureFormat,
-    window: Arc<Window>,
-    keys: HashSet<KeyCode>,
+    mipmap_generator: MipmapGenerator,
+    viewport_clear: ViewportClear,
+    frame: Cell<u64>,
 }
 
-impl Context {
+impl Gpu {
+    /// Picks an adapter that can present to `surface`, or any adapter without one. `None` if
+    /// there is no such adapter.
     pub(crate) async fn new(
-        adapter: &wgpu::Adapter,
-        format: wgpu::TextureFormat,
-        window: Arc<Window>,
-    ) -> Self {
+        instance: wgpu::Instance,
+        surface: Option<&wgpu::Surface<'_>>,
+    ) -> Option<Self> {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter: false,
+                compatible_surface: surface,
+            })
+            .await?;
         let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    required_features: adapter.features() & OPTIONAL_FEATURES,
+                    required_limits: wgpu::Limits {
+                        max_push_constant_size: adapter.limits().max_push_constant_size,
+                        ..Default::default()
+                    },
+                },
+                None,
+            )
             .await
             .unwrap();
-        Self {
+        let mipmap_generator = MipmapGenerator::new(&device);
+        Some(Self {
+            instance,
+            adapter,
             device,
             queue,
+            mipmap_generator,
+            viewport_clear: ViewportClear::default(),
+            frame: Cell::new(0),
+        })
+    }
+
+    pub(crate) fn instance(&self) -> &wgpu::Instance {
+        &self.instance
+    }
+
+    pub(crate) fn adapter(&self) -> &wgpu::Adapter {
+        &self.adapter
+    }
+}
+
+/// Opens a window once the event loop is reachable, see [`Context::open_window`].
+pub(crate) type WindowOpener = Box<dyn FnOnce(&ActiveEventLoop, Rc<Gpu>) -> Box<dyn AppWindow>>;
+
+pub struct Context {
+    gpu: Rc<Gpu>,
+    format: wgpu::TextureFormat,
+    /// `None` when replaying input headless.
+    window: Option<Arc<Window>>,
+    size: Vec2,
+    scale_factor: f32,
+    focused: bool,
+    occluded: bool,
+    input: Input,
+    depth_format: Cell<Option<DepthFormat>>,
+    exit_requested: Cell<bool>,
+    close_requested: Cell<bool>,
+    ime_allowed: Cell<bool>,
+    new_windows: RefCell<Vec<WindowOpener>>,
+}
+
+impl Context {
+    pub(crate) fn new(
+        gpu: Rc<Gpu>,
+        format: wgpu::TextureFormat,
+        window: Option<Arc<Window>>,
+        size: Vec2,
+    ) -> Self {
+        let scale_factor = window
+            .as_ref()
+            .map_or(1.0, |window| window.scale_factor() as f32);
+        let focused = window.as_ref().is_none_or(|window| window.has_focus());
+        Self {
+            gpu,
             format,
             window,
-            keys: HashSet::new(),
+            size,
+            scale_factor,
+            focused,
+            occluded: false,
+            input: Input::default(),
+            depth_format: Cell::new(Some(DepthFormat::Depth24Stencil8)),
+            exit_requested: Cell::new(false),
+            close_requested: Cell::new(false),
+            ime_allowed: Cell::new(false),
+            new_windows: RefCell::new(Vec::new()),
         }
     }
 
+    pub(crate) fn gpu(&self) -> &Rc<Gpu> {
+        &self.gpu
+    }
+
     pub(crate) fn device(&self) -> &wgpu::Device {
-        &self.device
+        &self.gpu.device
     }
 
     pub(crate) fn queue(&self) -> &wgpu::Queue {
-        &self.queue
+        &self.gpu.queue
     }
 
     pub(crate) fn format(&self) -> wgpu::TextureFormat {
         self.format
     }
 
+    pub(crate) fn features(&self) -> wgpu::Features {
+        self.gpu.device.features()
+    }
+
+    /// Counts frames of all windows, which share buffers.
+    pub(crate) fn frame_index(&self) -> u64 {
+        self.gpu.frame.get()
+    }
+
+    pub(crate) fn next_frame(&self) {
+        self.gpu.frame.set(self.gpu.frame.get() + 1);
+    }
+
+    /// Depth buffer of the window, `None` when frames are rendered without one.
+    pub fn depth_format(&self) -> Option<DepthFormat> {
+        self.depth_format.get()
+    }
+
+    /// Replaces the depth buffer of the window from the next frame on. Pipelines and bundles
+    /// adapt to it when they are next used.
+    pub fn set_depth_format(&self, format: Option<DepthFormat>) {
+        self.depth_format.set(format);
+    }
+
+    pub(crate) fn sample_count(&self) -> u32 {
+        SAMPLE_COUNT
+    }
+
+    pub(crate) fn viewport_clear(&self) -> &ViewportClear {
+        &self.gpu.viewport_clear
+    }
+
+    pub(crate) fn mipmap_generator(&self) -> &MipmapGenerator {
+        &self.gpu.mipmap_generator
+    }
+
+    /// Creates a context without a window or surface, rendering to nothing.
+    pub(crate) async fn headless(size: Vec2) -> Option<Self> {
+        let gpu = Gpu::new(wgpu::Instance::default(), None).await?;
+        Some(Self::new(
+            Rc::new(gpu),
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            None,
+            size,
+        ))
+    }
+
+    pub(crate) fn set_size(&mut self, size: Vec2) {
+        self.size = size;
+    }
+
+    /// Size of the window in physical pixels as of the last resize event.
     pub fn size(&self) -> Vec2 {
-        vec2(
-            self.window.inner_size().width as f32,
-            self.window.inner_size().height as f32,
-        )
+        self.size
+    }
+
+    /// Size of the window in logical pixels, which stays the same on displays with a higher
+    /// scale factor.
+    pub fn logical_size(&self) -> Vec2 {
+        self.to_logical(self.size)
+    }
+
+    /// Converts physical pixels to logical ones with the current scale factor.
+    pub fn to_logical(&self, physical: Vec2) -> Vec2 {
+        physical / self.scale_factor
+    }
+
+    pub fn to_physical(&self, logical: Vec2) -> Vec2 {
+        logical * self.scale_factor
+    }
+
+    pub(crate) fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
     }
 
+    /// Physical pixels per logical pixel of the monitor the window is on.
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    pub(crate) fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub(crate) fn set_occluded(&mut self, occluded: bool) {
+        self.occluded = occluded;
+    }
+
+    /// Whether the window is minimized or otherwise hidden.
+    pub fn is_occluded(&self) -> bool {
+        self.occluded
+    }
+
+    /// Moves the cursor to a position in physical pixels.
     pub fn set_cursor_position(&self, pos: Vec2) {
-        let _ = self
-            .window
-            .set_cursor_position(PhysicalPosition::new(pos.x, pos.y));
+        if let Some(window) = &self.window {
+            let _ = window.set_cursor_position(PhysicalPosition::new(pos.x, pos.y));
+        }
     }
 
+    /// Moves the cursor to a position in logical pixels.
+    pub fn set_logical_cursor_position(&self, pos: Vec2) {
+        if let Some(window) = &self.window {
+            let _ = window.set_cursor_position(LogicalPosition::new(pos.x, pos.y));
+        }
+    }
+
+    /// Keeps the cursor in place, or inside the window where that isn't supported.
     pub fn lock_cursor(&self) {
-        let _ = self.window.set_cursor_grab(CursorGrabMode::Locked);
+        if let Some(window) = &self.window {
+            if window.set_cursor_grab(CursorGrabMode::Locked).is_err() {
+                let _ = window.set_cursor_grab(CursorGrabMode::Confined);
+            }
+        }
     }
 
-    pub(crate) fn set_key(&mut self, code: KeyCode, pressed: bool) {
-        if pressed {
-            self.keys.insert(code);
-        } else {
-            self.keys.remove(&code);
+    pub fn unlock_cursor(&self) {
+        if let Some(window) = &self.window {
+            let _ = window.set_cursor_grab(CursorGrabMode::None);
         }
     }
 
+    pub fn set_cursor_visible(&self, visible: bool) {
+        if let Some(window) = &self.window {
+            window.set_cursor_visible(visible);
+        }
+    }
+
+    pub fn set_cursor_icon(&self, icon: CursorIcon) {
+        if let Some(window) = &self.window {
+            window.set_cursor(icon);
+        }
+    }
+
+    pub fn set_title(&self, title: &str) {
+        if let Some(window) = &self.window {
+            window.set_title(title);
+        }
+    }
+
+    /// Switches between a borderless fullscreen window on the current monitor and a normal one.
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        if let Some(window) = &self.window {
+            window.set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+        }
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.window
+            .as_ref()
+            .is_some_and(|window| window.fullscreen().is_some())
+    }
+
+    /// Asks for a new size in physical pixels. [`State::resize`](crate::State::resize) is called once the
+    /// window actually changes size.
+    pub fn set_inner_size(&self, size: Vec2) {
+        if let Some(window) = &self.window {
+            let _ = window.request_inner_size(PhysicalSize::new(size.x, size.y));
+        }
+    }
+
+    /// Asks for a new size in logical pixels, see [`Context::set_inner_size`].
+    pub fn set_logical_inner_size(&self, size: Vec2) {
+        if let Some(window) = &self.window {
+            let _ = window.request_inner_size(LogicalSize::new(size.x, size.y));
+        }
+    }
+
+    /// Decodes an image file and uses it as the window's icon.
+    pub fn set_window_icon(&self, data: &[u8]) -> ImageResult<()> {
+        if let Some(window) = &self.window {
+            let image = image::load_from_memory(data)?.into_rgba8();
+            let (width, height) = image.dimensions();
+            let icon = Icon::from_rgba(image.into_raw(), width, height).map_err(|error| {
+                ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic(
+                    error.to_string(),
+                )))
+            })?;
+            window.set_window_icon(Some(icon));
+        }
+        Ok(())
+    }
+
+    /// Closes every window and returns from [`run`](crate::run) after the current callback.
+    pub fn exit(&self) {
+        self.exit_requested.set(true);
+    }
+
+    pub(crate) fn exit_requested(&self) -> bool {
+        self.exit_requested.get()
+    }
+
+    /// Closes this window after the current callback. The application exits once no window is
+    /// left.
+    pub fn close(&self) {
+        self.close_requested.set(true);
+    }
+
+    pub(crate) fn close_requested(&self) -> bool {
+        self.close_requested.get()
+    }
+
+    /// Opens another window after the current callback, its state created by
+    /// [`State::new`](crate::State::new). All windows share the device, so textures, buffers
+    /// and pipelines can be used in any of them.
+    pub fn open_window<S: State + 'static>(&self) {
+        self.open_window_with(S::new);
+    }
+
+    /// Like [`Context::open_window`], creating the state with `new`, e.g. to hand it resources
+    /// shared with this window.
+    pub fn open_window_with<S: State + 'static>(&self, new: impl FnOnce(&Context) -> S + 'static) {
+        self.new_windows
+            .borrow_mut()
+            .push(Box::new(move |event_loop, gpu| {
+                Box::new(WindowState::with_state(
+                    event_loop,
+                    InputMode::Live,
+                    Some(gpu),
+                    new,
+                ))
+            }));
+    }
+
+    pub(crate) fn take_new_windows(&self) -> Vec<WindowOpener> {
+        self.new_windows.take()
+    }
+
+    /// Identifies the window, `None` when replaying headless.
+    pub fn window_id(&self) -> Option<WindowId> {
+        self.window.as_ref().map(|window| window.id())
+    }
+
+    /// Lets the IME compose text, for as long as a text field has focus. While it is allowed, text
+    /// only arrives through [`State::ime_commit`], not with key presses.
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.ime_allowed.set(allowed);
+        if let Some(window) = &self.window {
+            window.set_ime_allowed(allowed);
+        }
+    }
+
+    pub fn is_ime_allowed(&self) -> bool {
+        self.ime_allowed.get()
+    }
+
+    /// Area of the text being edited, in physical pixels, so the IME can place its candidate window next
+    /// to it.
+    pub fn set_ime_cursor_area(&self, position: Vec2, size: Vec2) {
+        if let Some(window) = &self.window {
+            window.set_ime_cursor_area(
+                PhysicalPosition::new(position.x, position.y),
+                PhysicalSize::new(size.x, size.y),
+            );
+        }
+    }
+
+    /// Area of the text being edited in logical pixels, see [`Context::set_ime_cursor_area`].
+    pub fn set_logical_ime_cursor_area(&self, position: Vec2, size: Vec2) {
+        if let Some(window) = &self.window {
+            window.set_ime_cursor_area(
+                LogicalPosition::new(position.x, position.y),
+                LogicalSize::new(size.x, size.y),
+            );
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        self.input.text()
+    }
+
+    pub fn touches(&self) -> &[Touch] {
+        self.input.touches()
+    }
+
+    pub fn gestures(&self) -> &[Gesture] {
+        self.input.gestures()
+    }
+
+    pub(crate) fn input_mut(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
+    /// Input as of the current update.
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
     pub fn key(&self, code: KeyCode) -> bool {
-        self.keys.contains(&code)
+        self.input.key(code)
+    }
+
+    pub fn key_pressed(&self, code: KeyCode) -> bool {
+        self.input.key_pressed(code)
+    }
+
+    pub fn key_released(&self, code: KeyCode) -> bool {
+        self.input.key_released(code)
+    }
+
+    pub fn mouse_button(&self, button: MouseButton) -> bool {
+        self.input.mouse_button(button)
+    }
+
+    pub fn mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.input.mouse_button_pressed(button)
+    }
+
+    pub fn mouse_button_released(&self, button: MouseButton) -> bool {
+        self.input.mouse_button_released(button)
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.input.modifiers()
+    }
+
+    /// Cursor position in physical pixels.
+    pub fn cursor(&self) -> Vec2 {
+        self.input.cursor()
+    }
+
+    pub fn logical_cursor(&self) -> Vec2 {
+        self.to_logical(self.input.cursor())
+    }
+
+    pub fn mouse_motion(&self) -> Vec2 {
+        self.input.mouse_motion()
+    }
+
+    pub fn scroll(&self) -> Vec2 {
+        self.input.scroll()
     }
 }
@@ -7,9 +7,9 @@ pub enum Format {
     Float32x3,
 }
 
-impl Into<wgpu::VertexFormat> for Format {
-    fn into(self) -> wgpu::VertexFormat {
-        match self {
+impl From<Format> for wgpu::VertexFormat {
+    fn from(format: Format) -> Self {
+        match format {
             Format::Float32x2 => wgpu::VertexFormat::Float32x2,
             Format::Float32x3 => wgpu::VertexFormat::Float32x3,
         }
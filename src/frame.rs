@@ -1,34 +1,169 @@
-use crate::Pass;
+use glam::*;
 
-pub struct Frame {
+use crate::{ComputePass, Context, DepthFormat, Pass, RenderTarget, Viewport};
+
+/// Formats of the attachments a pass renders to, which pipelines and bundles must match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Target {
+    pub(crate) format: wgpu::TextureFormat,
+    pub(crate) depth_format: Option<DepthFormat>,
+    pub(crate) sample_count: u32,
+}
+
+impl Target {
+    /// Target of the frames of the window.
+    pub(crate) fn window(ctx: &Context) -> Self {
+        Self {
+            format: ctx.format(),
+            depth_format: ctx.depth_format(),
+            sample_count: ctx.sample_count(),
+        }
+    }
+
+    /// Whether the color format can be blended into, which integer and 32 bit float formats
+    /// can't.
+    pub(crate) fn blendable(&self, device: &wgpu::Device) -> bool {
+        self.format
+            .guaranteed_format_features(device.features())
+            .flags
+            .contains(wgpu::TextureFormatFeatureFlags::BLENDABLE)
+    }
+}
+
+/// Values a pass starts with, `None` keeping what the target already holds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Clear {
+    pub color: Option<Vec4>,
+    pub depth: Option<f32>,
+    pub stencil: Option<u32>,
+}
+
+impl Clear {
+    /// Clears the color, depth to 1 and stencil to 0.
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self {
+            color: Some(vec4(r, g, b, a)),
+            depth: Some(1.0),
+            stencil: Some(0),
+        }
+    }
+
+    /// Keeps the color, depth and stencil.
+    pub fn load() -> Self {
+        Self {
+            color: None,
+            depth: None,
+            stencil: None,
+        }
+    }
+
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn with_stencil(mut self, stencil: u32) -> Self {
+        self.stencil = Some(stencil);
+        self
+    }
+}
+
+pub struct Frame<'a> {
+    context: &'a Context,
     texture: wgpu::SurfaceTexture,
     view: wgpu::TextureView,
+    depth_stencil: Option<&'a wgpu::TextureView>,
     encoder: wgpu::CommandEncoder,
 }
 
-impl Frame {
+impl<'a> Frame<'a> {
     pub(crate) fn new(
-        device: &wgpu::Device,
+        context: &'a Context,
         surface: &wgpu::Surface,
+        depth_stencil: Option<&'a wgpu::TextureView>,
     ) -> Result<Self, wgpu::SurfaceError> {
         let texture = surface.get_current_texture()?;
         let view = texture
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
-        let encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        context.next_frame();
+        let encoder = context
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
         Ok(Self {
+            context,
             texture,
             view,
+            depth_stencil,
             encoder,
         })
     }
 
-    pub fn pass(&mut self, r: f32, g: f32, b: f32, a: f32) -> Pass {
-        Pass::new(&mut self.encoder, &self.view, r, g, b, a)
+    pub(crate) fn context(&self) -> &'a Context {
+        self.context
+    }
+
+    pub fn size(&self) -> Vec2 {
+        vec2(
+            self.texture.texture.width() as f32,
+            self.texture.texture.height() as f32,
+        )
+    }
+
+    pub fn pass(&mut self, r: f32, g: f32, b: f32, a: f32) -> Pass<'_> {
+        self.pass_with(Clear::new(r, g, b, a))
+    }
+
+    pub fn pass_with(&mut self, clear: Clear) -> Pass<'_> {
+        Pass::new(
+            self.context,
+            &mut self.encoder,
+            &self.view,
+            self.depth_stencil,
+            Target::window(self.context),
+            clear,
+        )
+    }
+
+    /// Starts a pass drawing into `target` instead of the window.
+    pub fn target_pass<'b>(&'b mut self, target: &'b RenderTarget, clear: Clear) -> Pass<'b> {
+        Pass::new(
+            self.context,
+            &mut self.encoder,
+            target.view(),
+            target.depth_stencil(),
+            target.target(),
+            clear,
+        )
+    }
+
+    /// Like [`Frame::viewport`], drawing into `target`.
+    pub fn target_viewport<'b>(
+        &'b mut self,
+        target: &'b RenderTarget,
+        viewport: &Viewport,
+    ) -> Pass<'b> {
+        let mut pass = self.target_pass(target, Clear::load());
+        pass.restrict(viewport, target.size());
+        pass
+    }
+
+    pub fn compute(&mut self) -> ComputePass<'_> {
+        ComputePass::new(&mut self.encoder)
+    }
+
+    /// Starts a pass restricted to `viewport`, keeping whatever was drawn outside of it.
+    pub fn viewport(&mut self, viewport: &Viewport) -> Pass<'_> {
+        let size = self.size();
+        let mut pass = self.pass_with(Clear::load());
+        pass.restrict(viewport, size);
+        pass
     }
 
-    pub(crate) fn finish(self, queue: &wgpu::Queue) {
-        queue.submit(std::iter::once(self.encoder.finish()));
+    pub(crate) fn finish(self) {
+        self.context
+            .queue()
+            .submit(std::iter::once(self.encoder.finish()));
         self.texture.present();
     }
 }
@@ -1,19 +1,9 @@
-use std::sync::Arc;
+use std::{rc::Rc, sync::Arc};
 
+use glam::vec2;
 use winit::{dpi::PhysicalSize, window::Window};
 
-use crate::{Context, Frame, State};
-
-async fn request_adapter(instance: &wgpu::Instance, surface: &wgpu::Surface<'_>) -> wgpu::Adapter {
-    instance
-        .request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::default(),
-            force_fallback_adapter: false,
-            compatible_surface: Some(surface),
-        })
-        .await
-        .unwrap()
-}
+use crate::{context::Gpu, Context, DepthFormat, Frame, State};
 
 fn create_surface_configuration(
     adapter: &wgpu::Adapter,
@@ -39,25 +29,83 @@ fn create_surface_configuration(
     }
 }
 
+fn create_depth_stencil(
+    device: &wgpu::Device,
+    format: DepthFormat,
+    sample_count: u32,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: config.width.max(1),
+                height: config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: format.into(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
 pub struct GraphicsState {
     surface: wgpu::Surface<'static>,
     config: wgpu::SurfaceConfiguration,
+    depth_stencil: Option<(DepthFormat, wgpu::TextureView)>,
     context: Context,
 }
 
 impl GraphicsState {
-    pub async fn new(window: Arc<Window>) -> Self {
+    /// Creates the surface of a window, and the GPU state unless another window shares its own.
+    pub async fn new(window: Arc<Window>, gpu: Option<Rc<Gpu>>) -> Self {
         let size = window.inner_size();
-        let instance = wgpu::Instance::default();
-        let surface = instance.create_surface(window.clone()).unwrap();
-        let adapter = request_adapter(&instance, &surface).await;
-        let config = create_surface_configuration(&adapter, &surface, size);
-        let context = Context::new(&adapter, config.format, window).await;
-        Self {
+        let (gpu, surface) = match gpu {
+            Some(gpu) => {
+                let surface = gpu.instance().create_surface(window.clone()).unwrap();
+                (gpu, surface)
+            }
+            None => {
+                let instance = wgpu::Instance::default();
+                let surface = instance.create_surface(window.clone()).unwrap();
+                let gpu = Gpu::new(instance, Some(&surface))
+                    .await
+                    .expect("no GPU adapter can present to the window");
+                (Rc::new(gpu), surface)
+            }
+        };
+        let config = create_surface_configuration(gpu.adapter(), &surface, size);
+        let context = Context::new(
+            gpu,
+            config.format,
+            Some(window),
+            vec2(size.width as f32, size.height as f32),
+        );
+        let mut graphics = Self {
             surface,
             config,
+            depth_stencil: None,
             context,
-        }
+        };
+        graphics.update_depth_stencil();
+        graphics
+    }
+
+    /// Recreates the depth buffer when its format or the size of the window changed.
+    fn update_depth_stencil(&mut self) {
+        self.depth_stencil = self.context.depth_format().map(|format| {
+            let view = create_depth_stencil(
+                self.context.device(),
+                format,
+                self.context.sample_count(),
+                &self.config,
+            );
+            (format, view)
+        });
     }
 
     pub(crate) fn context_mut(&mut self) -> &mut Context {
@@ -73,6 +121,7 @@ impl GraphicsState {
             self.config.width = size.width;
             self.config.height = size.height;
             self.surface.configure(self.context.device(), &self.config);
+            self.update_depth_stencil();
         }
     }
 
@@ -80,10 +129,14 @@ impl GraphicsState {
         self.resize(PhysicalSize::new(self.config.width, self.config.height));
     }
 
-    pub fn render<T: State>(&self, user_state: &T) -> Result<(), wgpu::SurfaceError> {
-        let mut frame = Frame::new(self.context.device(), &self.surface)?;
+    pub fn render<T: State>(&mut self, user_state: &T) -> Result<(), wgpu::SurfaceError> {
+        if self.depth_stencil.as_ref().map(|(format, _)| *format) != self.context.depth_format() {
+            self.update_depth_stencil();
+        }
+        let depth_stencil = self.depth_stencil.as_ref().map(|(_, view)| view);
+        let mut frame = Frame::new(&self.context, &self.surface, depth_stencil)?;
         user_state.render(&mut frame);
-        frame.finish(self.context.queue());
+        frame.finish();
         Ok(())
     }
 }
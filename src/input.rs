@@ -0,0 +1,174 @@
+use std::{collections::HashSet, time::Duration};
+
+use glam::*;
+use winit::{
+    event::{MouseButton, TouchPhase},
+    keyboard::{KeyCode, ModifiersState},
+};
+
+use crate::{touch::GestureRecognizer, Gesture, Touch};
+
+/// Keyboard, mouse and touch state as seen by one [`State::update`](crate::State::update). Edges and
+/// deltas cover the events since the previous update.
+#[derive(Clone, Debug, Default)]
+pub struct Input {
+    keys: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
+    mouse_buttons: HashSet<MouseButton>,
+    mouse_buttons_pressed: HashSet<MouseButton>,
+    mouse_buttons_released: HashSet<MouseButton>,
+    modifiers: ModifiersState,
+    cursor: Vec2,
+    mouse_motion: Vec2,
+    scroll: Vec2,
+    text: String,
+    touches: Vec<Touch>,
+    gestures: Vec<Gesture>,
+    gesture_recognizer: GestureRecognizer,
+    /// Sum of the update deltas, so gestures time the same when replayed.
+    time: Duration,
+}
+
+impl Input {
+    pub(crate) fn set_key(&mut self, code: KeyCode, pressed: bool) {
+        if pressed {
+            if self.keys.insert(code) {
+                self.keys_pressed.insert(code);
+            }
+        } else if self.keys.remove(&code) {
+            self.keys_released.insert(code);
+        }
+    }
+
+    pub(crate) fn set_mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        if pressed {
+            if self.mouse_buttons.insert(button) {
+                self.mouse_buttons_pressed.insert(button);
+            }
+        } else if self.mouse_buttons.remove(&button) {
+            self.mouse_buttons_released.insert(button);
+        }
+    }
+
+    /// Releases everything held, for when the window loses focus and misses the releases.
+    pub(crate) fn release_all(&mut self) {
+        self.keys_released.extend(self.keys.drain());
+        self.mouse_buttons_released
+            .extend(self.mouse_buttons.drain());
+        self.modifiers = ModifiersState::empty();
+        self.touches.clear();
+        self.gesture_recognizer = GestureRecognizer::default();
+    }
+
+    pub(crate) fn set_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    pub(crate) fn set_cursor(&mut self, pos: Vec2) {
+        self.cursor = pos;
+    }
+
+    pub(crate) fn add_mouse_motion(&mut self, delta: Vec2) {
+        self.mouse_motion += delta;
+    }
+
+    pub(crate) fn add_scroll(&mut self, delta: Vec2) {
+        self.scroll += delta;
+    }
+
+    pub(crate) fn add_text(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    /// Tracks the finger and returns the gestures it completes.
+    pub(crate) fn touch(&mut self, touch: Touch) -> Vec<Gesture> {
+        let index = self.touches.iter().position(|other| other.id == touch.id);
+        let previous = index.map(|index| self.touches[index]);
+        match (touch.phase, index) {
+            (TouchPhase::Started | TouchPhase::Moved, Some(index)) => self.touches[index] = touch,
+            (TouchPhase::Started | TouchPhase::Moved, None) => self.touches.push(touch),
+            (TouchPhase::Ended | TouchPhase::Cancelled, Some(index)) => {
+                self.touches.remove(index);
+            }
+            (TouchPhase::Ended | TouchPhase::Cancelled, None) => {}
+        }
+        let gestures = self
+            .gesture_recognizer
+            .touch(touch, previous, &self.touches, self.time);
+        self.gestures.extend(&gestures);
+        gestures
+    }
+
+    /// Forgets edges and deltas once an update has seen them.
+    pub(crate) fn end_frame(&mut self, delta: Duration) {
+        self.time += delta;
+        self.gestures.clear();
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.mouse_buttons_pressed.clear();
+        self.mouse_buttons_released.clear();
+        self.mouse_motion = Vec2::ZERO;
+        self.scroll = Vec2::ZERO;
+        self.text.clear();
+    }
+
+    pub fn key(&self, code: KeyCode) -> bool {
+        self.keys.contains(&code)
+    }
+
+    /// Whether the key went down since the last update, ignoring key repeats.
+    pub fn key_pressed(&self, code: KeyCode) -> bool {
+        self.keys_pressed.contains(&code)
+    }
+
+    pub fn key_released(&self, code: KeyCode) -> bool {
+        self.keys_released.contains(&code)
+    }
+
+    pub fn mouse_button(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.contains(&button)
+    }
+
+    pub fn mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons_pressed.contains(&button)
+    }
+
+    pub fn mouse_button_released(&self, button: MouseButton) -> bool {
+        self.mouse_buttons_released.contains(&button)
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Last cursor position inside the window, in physical pixels from the top left corner.
+    pub fn cursor(&self) -> Vec2 {
+        self.cursor
+    }
+
+    /// Raw mouse movement since the last update.
+    pub fn mouse_motion(&self) -> Vec2 {
+        self.mouse_motion
+    }
+
+    /// Scroll in lines since the last update.
+    pub fn scroll(&self) -> Vec2 {
+        self.scroll
+    }
+
+    /// Text typed or committed with the IME since the last update.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Fingers on the screen.
+    pub fn touches(&self) -> &[Touch] {
+        &self.touches
+    }
+
+    /// Gestures recognized since the last update.
+    pub fn gestures(&self) -> &[Gesture] {
+        &self.gestures
+    }
+}
@@ -1,86 +1,267 @@
-use std::time::Duration;
+use std::{collections::HashMap, io, marker::PhantomData, path::Path, time::Duration};
 
 pub use glam;
 use glam::*;
-use window_state::WindowState;
+use recording::{Recording, RecordingWriter};
+use window_state::{AppWindow, InputMode, WindowState};
 use winit::{
     application::ApplicationHandler,
-    event::WindowEvent,
+    event::{DeviceEvent, WindowEvent},
     event_loop::{ActiveEventLoop, EventLoop},
-    window::WindowId,
 };
 
+mod action;
+mod atlas;
 mod bind_group;
 mod buffer;
+mod bundle;
+mod compressed;
+mod compute;
 mod context;
 mod frame;
 mod graphics_state;
+mod input;
+mod mipmap;
 mod pass;
 mod pipeline;
+mod recording;
+mod render_target;
+mod skybox;
 mod texture;
+mod touch;
+mod viewport;
+mod volume;
 mod window_state;
 
 pub mod data;
 pub mod uniform;
 pub mod vertex;
 
+pub use action::*;
+pub use atlas::*;
 pub use bind_group::*;
 pub use buffer::*;
+pub use bundle::*;
+pub use compressed::*;
+pub use compute::*;
 pub use context::*;
 pub use frame::*;
+pub use input::*;
 pub use pass::*;
 pub use pipeline::*;
+pub use recording::*;
+pub use render_target::*;
+pub use skybox::*;
 pub use texture::*;
+pub use touch::*;
+pub use viewport::*;
+pub use volume::*;
 
 pub type BufferAddress = wgpu::BufferAddress;
 pub type DynamicOffset = wgpu::DynamicOffset;
+pub type CompareFunction = wgpu::CompareFunction;
+pub type StencilOperation = wgpu::StencilOperation;
+pub type StencilFaceState = wgpu::StencilFaceState;
+pub type ShaderStages = wgpu::ShaderStages;
+pub type AddressMode = wgpu::AddressMode;
+pub type FilterMode = wgpu::FilterMode;
+pub type SamplerBorderColor = wgpu::SamplerBorderColor;
+pub type Face = wgpu::Face;
+pub type BlendState = wgpu::BlendState;
+pub type StorageAccess = wgpu::StorageTextureAccess;
 
-pub use winit::keyboard::KeyCode;
+pub use winit::{
+    event::{MouseButton, TouchPhase},
+    keyboard::{Key, KeyCode, ModifiersState, NamedKey},
+    window::{CursorIcon, WindowId},
+};
 
 pub trait State {
     fn new(context: &Context) -> Self;
-    fn resize(&mut self, _ctx: &Context, _size: Vec2) {}
+    /// Called with the new size in physical and logical pixels, also after the scale factor
+    /// changes, once the window has been resized for it.
+    fn resize(&mut self, _ctx: &Context, _size: Vec2, _logical_size: Vec2) {}
     fn key(&mut self, _ctx: &Context, _code: KeyCode, _pressed: bool) {}
+    /// The key as mapped by the keyboard layout, for shortcuts like Ctrl+Z that should follow the
+    /// label on the key. Called right after [`State::key`].
+    fn logical_key(&mut self, _ctx: &Context, _key: &Key, _pressed: bool) {}
+    /// Cursor position in physical pixels, see [`Context::to_logical`].
     fn cursor(&mut self, _ctx: &Context, _pos: Vec2) {}
+    fn mouse_button(&mut self, _ctx: &Context, _button: MouseButton, _pressed: bool) {}
+    /// Wheel or touchpad scroll in lines, positive `y` scrolling up.
+    fn scroll(&mut self, _ctx: &Context, _delta: Vec2) {}
+    /// Raw mouse movement, not affected by cursor acceleration, clamping or locking.
+    fn mouse_motion(&mut self, _ctx: &Context, _delta: Vec2) {}
+    /// Every change of a finger on a touch screen.
+    fn touch(&mut self, _ctx: &Context, _touch: Touch) {}
+    /// Gestures recognized from the touches, right after the touch completing them.
+    fn gesture(&mut self, _ctx: &Context, _gesture: Gesture) {}
+    /// Text typed on the keyboard, without control characters like backspace or enter.
+    fn text(&mut self, _ctx: &Context, _text: &str) {}
+    /// Text being composed with the IME, replacing the previous one, and the byte range of its
+    /// cursor. Empty text ends the composition.
+    fn ime_preedit(&mut self, _ctx: &Context, _text: &str, _cursor: Option<(usize, usize)>) {}
+    /// Text finished with the IME, passed on to [`State::text`] unless overridden.
+    fn ime_commit(&mut self, ctx: &Context, text: &str) {
+        self.text(ctx, text);
+    }
+    fn focused(&mut self, _ctx: &Context, _focused: bool) {}
+    /// Called when the window gets hidden, e.g. minimized, or shown again. Updates and rendering
+    /// are paused while it is hidden.
+    fn occluded(&mut self, _ctx: &Context, _occluded: bool) {}
+    fn scale_factor(&mut self, _ctx: &Context, _scale_factor: f32) {}
+    /// New position of the window's top left corner on the desktop, in physical pixels.
+    fn moved(&mut self, _ctx: &Context, _position: IVec2) {}
+    fn file_hovered(&mut self, _ctx: &Context, _path: &Path) {}
+    fn file_hover_cancelled(&mut self, _ctx: &Context) {}
+    fn file_dropped(&mut self, _ctx: &Context, _path: &Path) {}
     fn update(&mut self, _ctx: &Context, _delta: Duration) {}
     fn render(&self, frame: &mut Frame);
 }
 
 struct App<T> {
-    window: Option<WindowState<T>>,
+    windows: HashMap<WindowId, Box<dyn AppWindow>>,
+    input_mode: Option<InputMode>,
+    /// Whether the first window records or replays, which windows opened later don't.
+    recorded: bool,
+    state: PhantomData<T>,
 }
 
 impl<T> App<T> {
-    fn new() -> Self {
-        Self { window: None }
+    fn new(input_mode: InputMode) -> Self {
+        Self {
+            windows: HashMap::new(),
+            recorded: !matches!(input_mode, InputMode::Live),
+            input_mode: Some(input_mode),
+            state: PhantomData,
+        }
+    }
+
+    /// Opens the windows requested during the last callback and closes the ones that asked to.
+    fn update_windows(&mut self, event_loop: &ActiveEventLoop) {
+        let mut openers = Vec::new();
+        for window in self.windows.values() {
+            let ctx = window.context();
+            openers.extend(
+                ctx.take_new_windows()
+                    .into_iter()
+                    .map(|open| (open, ctx.gpu().clone())),
+            );
+        }
+        if self.recorded && !openers.is_empty() {
+            log::warn!("windows opened while recording or replaying take live input");
+        }
+        for (open, gpu) in openers {
+            let window = open(event_loop, gpu);
+            if let Some(id) = window.context().window_id() {
+                self.windows.insert(id, window);
+            }
+        }
+        self.windows.retain(|_, window| {
+            let close = window.context().close_requested();
+            if close {
+                window.exit();
+            }
+            !close
+        });
+        if self.windows.is_empty() && self.input_mode.is_none() {
+            event_loop.exit();
+        }
     }
 }
 
-impl<T: State> ApplicationHandler for App<T> {
+impl<T: State + 'static> ApplicationHandler for App<T> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        self.window = Some(window_state::WindowState::new(event_loop));
+        if let Some(input_mode) = self.input_mode.take() {
+            let window = WindowState::<T>::new(event_loop, input_mode);
+            if let Some(id) = window.context().window_id() {
+                self.windows.insert(id, Box::new(window));
+            }
+            self.update_windows(event_loop);
+        }
     }
 
     fn window_event(
         &mut self,
         event_loop: &ActiveEventLoop,
-        _window_id: WindowId,
+        window_id: WindowId,
         event: WindowEvent,
     ) {
-        if let Some(window) = self.window.as_mut() {
+        if let Some(window) = self.windows.get_mut(&window_id) {
             window.event(event_loop, event);
         }
+        self.update_windows(event_loop);
+    }
+
+    fn device_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _device_id: winit::event::DeviceId,
+        event: DeviceEvent,
+    ) {
+        for window in self.windows.values_mut() {
+            window.device_event(event_loop, event.clone());
+        }
+        self.update_windows(event_loop);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        for window in self.windows.values_mut() {
+            window.update(event_loop);
+        }
+        self.update_windows(event_loop);
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
-        if let Some(state) = self.window.as_mut() {
-            state.update();
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        for window in self.windows.values_mut() {
+            window.exit();
         }
     }
 }
 
-pub fn run<T: State>() {
+fn run_with<T: State + 'static>(input_mode: InputMode) {
     env_logger::init();
     let event_loop = EventLoop::new().unwrap();
-    event_loop.run_app(&mut App::<T>::new()).unwrap();
+    event_loop.run_app(&mut App::<T>::new(input_mode)).unwrap();
+}
+
+pub fn run<T: State + 'static>() {
+    run_with::<T>(InputMode::Live);
+}
+
+/// Runs like [`run`] and writes every input event and update delta to `path` while running.
+/// Fails if the file can't be created, write errors later on stop the recording. Only the first
+/// window is recorded, windows opened with [`Context::open_window`] take live input.
+pub fn record<T: State + 'static>(path: impl AsRef<Path>) -> io::Result<()> {
+    let writer = RecordingWriter::create(path.as_ref())?;
+    run_with::<T>(InputMode::Record(writer));
+    Ok(())
+}
+
+/// Runs a file saved by [`record`] in a window, ignoring live input, and exits at its end.
+/// Windows opened with [`Context::open_window`] take live input.
+pub fn replay<T: State + 'static>(path: impl AsRef<Path>) -> Result<(), ReplayError> {
+    run_with::<T>(InputMode::Replay(Recording::load(path.as_ref())?));
+    Ok(())
+}
+
+/// Replays a file saved by [`record`] without a window, calling every callback but
+/// [`State::render`], and returns the final state. Windows opened with
+/// [`Context::open_window`] are not opened.
+pub fn replay_headless<T: State>(path: impl AsRef<Path>) -> Result<T, ReplayError> {
+    let mut recording = Recording::load(path.as_ref())?;
+    let size = recording.initial_size().unwrap_or(Vec2::ZERO);
+    let mut context = pollster::block_on(Context::headless(size)).ok_or(ReplayError::NoAdapter)?;
+    let mut state = T::new(&context);
+    loop {
+        let replaying =
+            !context.exit_requested() && recording.replay_update(&mut context, &mut state);
+        if !context.take_new_windows().is_empty() {
+            log::warn!("windows can't be opened when replaying headless");
+        }
+        if !replaying {
+            break;
+        }
+    }
+    Ok(state)
 }
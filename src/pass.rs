@@ -2,43 +2,238 @@ use std::ops::Range;
 
 use glam::*;
 
-pub struct Pass<'a>(pub(crate) wgpu::RenderPass<'a>);
+use wgpu::util::RenderEncoder;
+
+use crate::{
+    frame::Target, pipeline::BundleSlot, uniform, Clear, Context, DrawIndexedIndirectArgs,
+    DrawIndirectArgs, IndirectBuffer, PushConstants, RenderBundle, Viewport,
+};
+
+pub(crate) enum Encoder<'a> {
+    Pass(wgpu::RenderPass<'a>),
+    Bundle(wgpu::RenderBundleEncoder<'a>),
+}
+
+pub struct Pass<'a> {
+    pub(crate) raw: Encoder<'a>,
+    pub(crate) context: &'a Context,
+    pub(crate) target: Target,
+    pub(crate) push_constants: Option<&'a PushConstants>,
+    /// Push constant slots taken while recording a bundle.
+    pub(crate) bundle_slots: Vec<BundleSlot>,
+}
 
 impl<'a> Pass<'a> {
     pub(crate) fn new(
+        context: &'a Context,
         encoder: &'a mut wgpu::CommandEncoder,
         view: &'a wgpu::TextureView,
-        r: f32,
-        g: f32,
-        b: f32,
-        a: f32,
+        depth_stencil: Option<&'a wgpu::TextureView>,
+        target: Target,
+        clear: Clear,
     ) -> Self {
-        Self(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        fn ops<T>(clear: Option<T>) -> wgpu::Operations<T> {
+            wgpu::Operations {
+                load: clear.map_or(wgpu::LoadOp::Load, wgpu::LoadOp::Clear),
+                store: wgpu::StoreOp::Store,
+            }
+        }
+        let color = clear.color.map(|color| wgpu::Color {
+            r: color.x as f64,
+            g: color.y as f64,
+            b: color.z as f64,
+            a: color.w as f64,
+        });
+        let depth_stencil_attachment =
+            depth_stencil
+                .zip(target.depth_format)
+                .map(|(view, format)| wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(ops(clear.depth)),
+                    stencil_ops: format.has_stencil().then(|| ops(clear.stencil)),
+                });
+        let raw = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
+                view,
                 resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: r as f64,
-                        g: g as f64,
-                        b: b as f64,
-                        a: a as f64,
-                    }),
-                    store: wgpu::StoreOp::Store,
-                },
+                ops: ops(color),
             })],
-            depth_stencil_attachment: None,
+            depth_stencil_attachment,
             timestamp_writes: None,
             occlusion_query_set: None,
-        }))
+        });
+        Self {
+            raw: Encoder::Pass(raw),
+            context,
+            target,
+            push_constants: None,
+            bundle_slots: Vec::new(),
+        }
+    }
+
+    pub(crate) fn bundle(
+        context: &'a Context,
+        encoder: wgpu::RenderBundleEncoder<'a>,
+        target: Target,
+    ) -> Self {
+        Self {
+            raw: Encoder::Bundle(encoder),
+            context,
+            target,
+            push_constants: None,
+            bundle_slots: Vec::new(),
+        }
+    }
+
+    pub(crate) fn encoder(&mut self) -> &mut dyn RenderEncoder<'a> {
+        match &mut self.raw {
+            Encoder::Pass(pass) => pass,
+            Encoder::Bundle(bundle) => bundle,
+        }
+    }
+
+    pub(crate) fn is_bundle(&self) -> bool {
+        matches!(self.raw, Encoder::Bundle(_))
+    }
+
+    /// State that only exists in a real render pass and cannot be recorded into a bundle.
+    pub(crate) fn render_pass(&mut self, what: &str) -> Option<&mut wgpu::RenderPass<'a>> {
+        match &mut self.raw {
+            Encoder::Pass(pass) => Some(pass),
+            Encoder::Bundle(_) => {
+                log::warn!("{what} is not available while recording a render bundle");
+                None
+            }
+        }
+    }
+
+    /// Restricts drawing to `viewport` of a target `size` pixels large and clears it if asked.
+    pub(crate) fn restrict(&mut self, viewport: &Viewport, size: Vec2) {
+        let (position, size) = viewport.rect(size);
+        self.set_viewport(position, size);
+        self.set_scissor(position, size);
+        if let Some(color) = viewport.clear {
+            let context = self.context;
+            context.viewport_clear().draw(self, color);
+        }
+    }
+
+    pub fn set_viewport(&mut self, position: Vec2, size: Vec2) {
+        if let Some(pass) = self.render_pass("set_viewport") {
+            pass.set_viewport(position.x, position.y, size.x, size.y, 0.0, 1.0);
+        }
+    }
+
+    pub fn set_scissor(&mut self, position: Vec2, size: Vec2) {
+        if let Some(pass) = self.render_pass("set_scissor") {
+            pass.set_scissor_rect(
+                position.x as u32,
+                position.y as u32,
+                size.x as u32,
+                size.y as u32,
+            );
+        }
+    }
+
+    pub fn set_stencil_reference(&mut self, reference: u32) {
+        if let Some(pass) = self.render_pass("set_stencil_reference") {
+            pass.set_stencil_reference(reference);
+        }
+    }
+
+    /// Replays bundles, recording them first if they were never used with the pass's target.
+    pub fn execute_bundles<I: IntoIterator<Item = &'a RenderBundle>>(&mut self, bundles: I) {
+        let (context, target) = (self.context, self.target);
+        let bundles = bundles
+            .into_iter()
+            .map(|bundle| bundle.get(context, target))
+            .collect::<Vec<_>>();
+        if let Some(pass) = self.render_pass("execute_bundles") {
+            pass.execute_bundles(bundles);
+        }
+        self.push_constants = None;
+    }
+
+    /// Sets the push constants declared by the attached pipeline.
+    pub fn set_push_constants<T: uniform::Uniform>(&mut self, value: &T) {
+        match self.push_constants {
+            Some(push_constants) => push_constants.set(self, bytemuck::bytes_of(value)),
+            None => log::warn!("attached pipeline has no push constants"),
+        }
     }
 
     pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
-        self.0.draw(vertices, instances);
+        self.encoder().draw(vertices, instances);
     }
 
     pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
-        self.0.draw_indexed(indices, base_vertex, instances);
+        self.encoder().draw_indexed(indices, base_vertex, instances);
+    }
+
+    pub fn draw_indirect(&mut self, buffer: &'a IndirectBuffer<DrawIndirectArgs>, index: u32) {
+        self.encoder().draw_indirect(
+            &buffer.buffer,
+            IndirectBuffer::<DrawIndirectArgs>::offset(index),
+        );
+    }
+
+    pub fn draw_indexed_indirect(
+        &mut self,
+        buffer: &'a IndirectBuffer<DrawIndexedIndirectArgs>,
+        index: u32,
+    ) {
+        self.encoder().draw_indexed_indirect(
+            &buffer.buffer,
+            IndirectBuffer::<DrawIndexedIndirectArgs>::offset(index),
+        );
+    }
+
+    /// Issues one draw per element of `draws`, in a single call when the adapter supports
+    /// `Features::MULTI_DRAW_INDIRECT` and the pass is not recording a bundle.
+    pub fn multi_draw_indirect(
+        &mut self,
+        buffer: &'a IndirectBuffer<DrawIndirectArgs>,
+        draws: Range<u32>,
+    ) {
+        let multi_draw = self.multi_draw();
+        match &mut self.raw {
+            Encoder::Pass(pass) if multi_draw => pass.multi_draw_indirect(
+                &buffer.buffer,
+                IndirectBuffer::<DrawIndirectArgs>::offset(draws.start),
+                draws.len() as u32,
+            ),
+            _ => {
+                for index in draws {
+                    self.draw_indirect(buffer, index);
+                }
+            }
+        }
+    }
+
+    pub fn multi_draw_indexed_indirect(
+        &mut self,
+        buffer: &'a IndirectBuffer<DrawIndexedIndirectArgs>,
+        draws: Range<u32>,
+    ) {
+        let multi_draw = self.multi_draw();
+        match &mut self.raw {
+            Encoder::Pass(pass) if multi_draw => pass.multi_draw_indexed_indirect(
+                &buffer.buffer,
+                IndirectBuffer::<DrawIndexedIndirectArgs>::offset(draws.start),
+                draws.len() as u32,
+            ),
+            _ => {
+                for index in draws {
+                    self.draw_indexed_indirect(buffer, index);
+                }
+            }
+        }
+    }
+
+    fn multi_draw(&self) -> bool {
+        self.context
+            .features()
+            .contains(wgpu::Features::MULTI_DRAW_INDIRECT)
     }
 }
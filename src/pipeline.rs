@@ -1,23 +1,233 @@
-use crate::{vertex, Context, Pass};
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
 
-pub struct Shader(pub(crate) wgpu::ShaderModule);
+use elsa::{FrozenMap, FrozenVec};
+
+use crate::{frame::Target, StencilFaceState};
+use crate::{uniform, vertex, BlendState, CompareFunction, Context, Face, Pass, ShaderStages};
+
+/// Values in each buffer emulating push constants.
+const PUSH_CONSTANT_SLOTS: u32 = 256;
+
+fn create_shader_module(device: &wgpu::Device, src: &str) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(src.into()),
+    })
+}
+
+pub struct Shader {
+    pub(crate) module: Rc<wgpu::ShaderModule>,
+    src: String,
+}
 
 impl Shader {
     pub fn new(ctx: &Context, src: String) -> Self {
-        Self(
-            ctx.device()
-                .create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: None,
-                    source: wgpu::ShaderSource::Wgsl(src.into()),
+        Self {
+            module: Rc::new(create_shader_module(ctx.device(), &src)),
+            src,
+        }
+    }
+}
+
+/// Buffer of [`PUSH_CONSTANT_SLOTS`] push constant values with the bind group using it.
+struct Chunk {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Slots of the push constant chunks kept by render bundles.
+#[derive(Default)]
+pub(crate) struct BundleSlots {
+    count: Cell<u32>,
+    free: RefCell<Vec<u32>>,
+}
+
+/// Slot recorded into a render bundle, given back when the bundle is dropped.
+pub(crate) struct BundleSlot {
+    slots: Rc<BundleSlots>,
+    slot: u32,
+}
+
+impl Drop for BundleSlot {
+    fn drop(&mut self) {
+        self.slots.free.borrow_mut().push(self.slot);
+    }
+}
+
+/// Push constants emulated with dynamically offset uniform buffers. Passes take new slots every
+/// frame, starting over at the first one, while bundles keep theirs until they are dropped.
+/// Chunks are added whenever the slots run out.
+pub(crate) struct FallbackPushConstants {
+    index: u32,
+    size: u32,
+    stride: u32,
+    layout: wgpu::BindGroupLayout,
+    frame_chunks: FrozenVec<Box<Chunk>>,
+    frame: Cell<u64>,
+    next_slot: Cell<u32>,
+    bundle_chunks: FrozenVec<Box<Chunk>>,
+    bundle_slots: Rc<BundleSlots>,
+}
+
+impl FallbackPushConstants {
+    pub(crate) fn new(ctx: &Context, index: u32, stages: ShaderStages, size: u32) -> Self {
+        let alignment = ctx.device().limits().min_uniform_buffer_offset_alignment;
+        let layout = ctx
+            .device()
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: stages,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(size as u64),
+                    },
+                    count: None,
+                }],
+            });
+        Self {
+            index,
+            size,
+            stride: size.div_ceil(alignment) * alignment,
+            layout,
+            frame_chunks: FrozenVec::new(),
+            frame: Cell::new(ctx.frame_index()),
+            next_slot: Cell::new(0),
+            bundle_chunks: FrozenVec::new(),
+            bundle_slots: Rc::default(),
+        }
+    }
+
+    pub(crate) fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    fn create_chunk(&self, ctx: &Context) -> Box<Chunk> {
+        let buffer = ctx.device().create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (self.stride * PUSH_CONSTANT_SLOTS) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = ctx.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(self.size as u64),
                 }),
-        )
+            }],
+        });
+        Box::new(Chunk { buffer, bind_group })
+    }
+
+    /// Chunk holding `slot`, created along with any chunk before it that is still missing.
+    fn chunk<'a>(&self, ctx: &Context, chunks: &'a FrozenVec<Box<Chunk>>, slot: u32) -> &'a Chunk {
+        let index = (slot / PUSH_CONSTANT_SLOTS) as usize;
+        while chunks.len() <= index {
+            chunks.push(self.create_chunk(ctx));
+        }
+        chunks.get(index).unwrap()
+    }
+
+    pub(crate) fn set<'a>(&'a self, pass: &mut Pass<'a>, data: &[u8]) {
+        let ctx = pass.context;
+        let (chunks, slot) = if pass.is_bundle() {
+            let slots = &self.bundle_slots;
+            let slot = slots.free.borrow_mut().pop().unwrap_or_else(|| {
+                let slot = slots.count.get();
+                slots.count.set(slot + 1);
+                slot
+            });
+            pass.bundle_slots.push(BundleSlot {
+                slots: slots.clone(),
+                slot,
+            });
+            (&self.bundle_chunks, slot)
+        } else {
+            if self.frame.get() != ctx.frame_index() {
+                self.frame.set(ctx.frame_index());
+                self.next_slot.set(0);
+            }
+            let slot = self.next_slot.get();
+            self.next_slot.set(slot + 1);
+            (&self.frame_chunks, slot)
+        };
+        let chunk = self.chunk(ctx, chunks, slot);
+        let offset = slot % PUSH_CONSTANT_SLOTS * self.stride;
+        ctx.queue().write_buffer(&chunk.buffer, offset as u64, data);
+        pass.encoder()
+            .set_bind_group(self.index, &chunk.bind_group, &[offset]);
+    }
+}
+
+/// Push constants of a pipeline, emulated when the adapter lacks `Features::PUSH_CONSTANTS`.
+pub(crate) enum PushConstants {
+    Native { stages: ShaderStages },
+    Fallback(FallbackPushConstants),
+}
+
+impl PushConstants {
+    pub(crate) fn set<'a>(&'a self, pass: &mut Pass<'a>, data: &[u8]) {
+        match self {
+            Self::Native { stages } => pass.encoder().set_push_constants(*stages, 0, data),
+            Self::Fallback(fallback) => fallback.set(pass, data),
+        }
     }
 }
 
+/// Turns the `var<push_constant>` declarations of `src` into uniforms bound at `group`.
+fn push_constants_to_uniform(src: &str, group: u32) -> wgpu::naga::Module {
+    let mut module = wgpu::naga::front::wgsl::parse_str(src)
+        .unwrap_or_else(|error| panic!("{}", error.emit_to_string(src)));
+    for (_, variable) in module.global_variables.iter_mut() {
+        if variable.space == wgpu::naga::AddressSpace::PushConstant {
+            variable.space = wgpu::naga::AddressSpace::Uniform;
+            variable.binding = Some(wgpu::naga::ResourceBinding { group, binding: 0 });
+        }
+    }
+    module
+}
+
+/// Whether `fs_main` writes floats, as the window's format needs. Shaders writing integers only
+/// work with integer targets, so none of their variants is created up front.
+fn writes_floats(src: &str) -> bool {
+    let Ok(module) = wgpu::naga::front::wgsl::parse_str(src) else {
+        return true;
+    };
+    module
+        .entry_points
+        .iter()
+        .filter(|entry_point| entry_point.name == "fs_main")
+        .filter_map(|entry_point| entry_point.function.result.as_ref())
+        .all(|result| match module.types[result.ty].inner {
+            wgpu::naga::TypeInner::Scalar(scalar)
+            | wgpu::naga::TypeInner::Vector { scalar, .. } => {
+                scalar.kind == wgpu::naga::ScalarKind::Float
+            }
+            _ => true,
+        })
+}
+
 pub struct PipelineBuilder<'a> {
     shader: &'a Shader,
     buffers: Vec<vertex::Layout>,
     bind_group_layouts: Vec<wgpu::BindGroupLayout>,
+    depth_compare: CompareFunction,
+    depth_write: bool,
+    stencil: wgpu::StencilState,
+    push_constants: Option<(ShaderStages, u32)>,
+    cull_mode: Option<Face>,
+    blend: BlendState,
 }
 
 impl<'a> PipelineBuilder<'a> {
@@ -26,9 +236,62 @@ impl<'a> PipelineBuilder<'a> {
             shader,
             buffers: Vec::new(),
             bind_group_layouts: Vec::new(),
+            depth_compare: CompareFunction::Always,
+            depth_write: false,
+            stencil: wgpu::StencilState::default(),
+            push_constants: None,
+            cull_mode: Some(Face::Back),
+            blend: BlendState::REPLACE,
         }
     }
 
+    /// Faces to skip, back faces by default. `None` draws both sides.
+    pub fn with_cull_mode(mut self, cull_mode: Option<Face>) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    /// Blending of the color target, left out for targets that can't be blended like integer
+    /// and 32 bit float formats.
+    pub fn with_blend(mut self, blend: BlendState) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Depth test of targets with a depth buffer, the format coming from the target.
+    pub fn with_depth(mut self, compare: CompareFunction, write: bool) -> Self {
+        self.depth_compare = compare;
+        self.depth_write = write;
+        self
+    }
+
+    pub fn with_stencil(self, face: StencilFaceState, read_mask: u32, write_mask: u32) -> Self {
+        self.with_stencil_faces(face, face, read_mask, write_mask)
+    }
+
+    pub fn with_stencil_faces(
+        mut self,
+        front: StencilFaceState,
+        back: StencilFaceState,
+        read_mask: u32,
+        write_mask: u32,
+    ) -> Self {
+        self.stencil = wgpu::StencilState {
+            front,
+            back,
+            read_mask,
+            write_mask,
+        };
+        self
+    }
+
+    /// Declares a `var<push_constant>` block of type `T`. Without adapter support it is turned
+    /// into a uniform buffer bound after all other bind groups.
+    pub fn with_push_constants<T: uniform::Uniform>(mut self, stages: ShaderStages) -> Self {
+        self.push_constants = Some((stages, std::mem::size_of::<T>() as u32));
+        self
+    }
+
     pub fn with_vertex<T: vertex::Vertex>(mut self) -> Self {
         self.buffers.push(T::layout());
         self
@@ -39,70 +302,149 @@ impl<'a> PipelineBuilder<'a> {
     }
 
     pub fn build(self, ctx: &Context) -> Pipeline {
-        Pipeline::new(ctx.device(), ctx.format(), self)
+        Pipeline::new(ctx, self)
     }
 }
 
-pub struct Pipeline(wgpu::RenderPipeline);
+/// Render pipeline created for the target of each pass it is attached to, so it works with
+/// any color format and with or without a depth buffer.
+pub struct Pipeline {
+    module: Rc<wgpu::ShaderModule>,
+    layout: wgpu::PipelineLayout,
+    buffers: Vec<vertex::Layout>,
+    depth_compare: CompareFunction,
+    depth_write: bool,
+    stencil: wgpu::StencilState,
+    cull_mode: Option<Face>,
+    blend: BlendState,
+    variants: FrozenMap<Target, Box<wgpu::RenderPipeline>>,
+    push_constants: Option<PushConstants>,
+}
 
 impl Pipeline {
-    fn new(device: &wgpu::Device, format: wgpu::TextureFormat, builder: PipelineBuilder) -> Self {
-        let buffsers = builder
-            .buffers
-            .iter()
-            .map(|layout| layout.as_vertex_buffer_layout())
-            .collect::<Box<_>>();
-        let bind_group_layouts = builder
-            .bind_group_layouts
-            .iter()
-            .map(|layout| layout)
-            .collect::<Box<_>>();
+    fn new(ctx: &Context, builder: PipelineBuilder) -> Self {
+        let device = ctx.device();
+        let mut push_constant_ranges = Vec::new();
+        let mut patched_module = None;
+        let push_constants = builder.push_constants.map(|(stages, size)| {
+            if ctx.features().contains(wgpu::Features::PUSH_CONSTANTS)
+                && size <= device.limits().max_push_constant_size
+            {
+                push_constant_ranges.push(wgpu::PushConstantRange {
+                    stages,
+                    range: 0..size,
+                });
+                PushConstants::Native { stages }
+            } else {
+                let index = builder.bind_group_layouts.len() as u32;
+                let module = push_constants_to_uniform(&builder.shader.src, index);
+                patched_module = Some(device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: None,
+                    source: wgpu::ShaderSource::Naga(Cow::Owned(module)),
+                }));
+                PushConstants::Fallback(FallbackPushConstants::new(ctx, index, stages, size))
+            }
+        });
+        let module = patched_module.map_or_else(|| builder.shader.module.clone(), Rc::new);
+        let mut bind_group_layouts = builder.bind_group_layouts.iter().collect::<Vec<_>>();
+        if let Some(PushConstants::Fallback(fallback)) = &push_constants {
+            bind_group_layouts.push(&fallback.layout);
+        }
         let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &bind_group_layouts,
-            push_constant_ranges: &[],
+            push_constant_ranges: &push_constant_ranges,
         });
-        Self(
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let pipeline = Self {
+            module,
+            layout,
+            buffers: builder.buffers,
+            depth_compare: builder.depth_compare,
+            depth_write: builder.depth_write,
+            stencil: builder.stencil,
+            cull_mode: builder.cull_mode,
+            blend: builder.blend,
+            variants: FrozenMap::new(),
+            push_constants,
+        };
+        // The window's variant is created right away, so errors in the shader show up here.
+        if writes_floats(&builder.shader.src) {
+            pipeline.variant(ctx, Target::window(ctx));
+        }
+        pipeline
+    }
+
+    fn variant(&self, ctx: &Context, target: Target) -> &wgpu::RenderPipeline {
+        if let Some(pipeline) = self.variants.get(&target) {
+            return pipeline;
+        }
+        let buffers = self
+            .buffers
+            .iter()
+            .map(|layout| layout.as_vertex_buffer_layout())
+            .collect::<Box<_>>();
+        let blendable = target.blendable(ctx.device());
+        if self.blend != BlendState::REPLACE && !blendable {
+            log::warn!(
+                "{:?} can't be blended, drawing without blending",
+                target.format
+            );
+        }
+        let pipeline = ctx
+            .device()
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                 label: None,
-                layout: Some(&layout),
+                layout: Some(&self.layout),
                 vertex: wgpu::VertexState {
-                    module: &builder.shader.0,
+                    module: &self.module,
                     entry_point: "vs_main",
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
-                    buffers: &buffsers,
+                    buffers: &buffers,
                 },
                 primitive: wgpu::PrimitiveState {
                     topology: wgpu::PrimitiveTopology::TriangleList,
                     strip_index_format: None,
                     front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
+                    cull_mode: self.cull_mode,
                     unclipped_depth: false,
                     polygon_mode: wgpu::PolygonMode::Fill,
                     conservative: false,
                 },
-                depth_stencil: None,
+                depth_stencil: target.depth_format.map(|format| wgpu::DepthStencilState {
+                    format: format.into(),
+                    depth_write_enabled: self.depth_write,
+                    depth_compare: self.depth_compare,
+                    stencil: if format.has_stencil() {
+                        self.stencil.clone()
+                    } else {
+                        wgpu::StencilState::default()
+                    },
+                    bias: wgpu::DepthBiasState::default(),
+                }),
                 multisample: wgpu::MultisampleState {
-                    count: 1,
+                    count: target.sample_count,
                     mask: !0,
                     alpha_to_coverage_enabled: false,
                 },
                 fragment: Some(wgpu::FragmentState {
-                    module: &builder.shader.0,
+                    module: &self.module,
                     entry_point: "fs_main",
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                     targets: &[Some(wgpu::ColorTargetState {
-                        format,
-                        blend: Some(wgpu::BlendState::REPLACE),
+                        format: target.format,
+                        blend: (self.blend != BlendState::REPLACE && blendable)
+                            .then_some(self.blend),
                         write_mask: wgpu::ColorWrites::ALL,
                     })],
                 }),
                 multiview: None,
-            }),
-        )
+            });
+        self.variants.insert(target, Box::new(pipeline))
     }
 
     pub fn attach<'a>(&'a self, pass: &mut Pass<'a>) {
-        pass.0.set_pipeline(&self.0);
+        let pipeline = self.variant(pass.context, pass.target);
+        pass.encoder().set_pipeline(pipeline);
+        pass.push_constants = self.push_constants.as_ref();
     }
 }
@@ -0,0 +1,470 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use glam::*;
+use winit::{
+    event::{MouseButton, TouchPhase},
+    keyboard::{Key, KeyCode, ModifiersState, NamedKey, NativeKey},
+};
+
+use crate::{Binding, Context, State, Touch};
+
+/// Every named key, to find them by their `Debug` name when reading a recording.
+#[rustfmt::skip]
+const NAMED_KEYS: &[NamedKey] = {
+    use NamedKey::*;
+    &[
+        Alt, AltGraph, CapsLock, Control, Fn, FnLock, NumLock, ScrollLock, Shift, Symbol,
+        SymbolLock, Meta, Hyper, Super, Enter, Tab, Space, ArrowDown, ArrowLeft, ArrowRight,
+        ArrowUp, End, Home, PageDown, PageUp, Backspace, Clear, Copy, CrSel, Cut, Delete, EraseEof,
+        ExSel, Insert, Paste, Redo, Undo, Accept, Again, Attn, Cancel, ContextMenu, Escape, Execute,
+        Find, Help, Pause, Play, Props, Select, ZoomIn, ZoomOut, BrightnessDown, BrightnessUp,
+        Eject, LogOff, Power, PowerOff, PrintScreen, Hibernate, Standby, WakeUp, AllCandidates,
+        Alphanumeric, CodeInput, Compose, Convert, FinalMode, GroupFirst, GroupLast, GroupNext,
+        GroupPrevious, ModeChange, NextCandidate, NonConvert, PreviousCandidate, Process,
+        SingleCandidate, HangulMode, HanjaMode, JunjaMode, Eisu, Hankaku, Hiragana,
+        HiraganaKatakana, KanaMode, KanjiMode, Katakana, Romaji, Zenkaku, ZenkakuHankaku, Soft1,
+        Soft2, Soft3, Soft4, ChannelDown, ChannelUp, Close, MailForward, MailReply, MailSend,
+        MediaClose, MediaFastForward, MediaPause, MediaPlay, MediaPlayPause, MediaRecord,
+        MediaRewind, MediaStop, MediaTrackNext, MediaTrackPrevious, New, Open, Print, Save,
+        SpellCheck, Key11, Key12, AudioBalanceLeft, AudioBalanceRight, AudioBassBoostDown,
+        AudioBassBoostToggle, AudioBassBoostUp, AudioFaderFront, AudioFaderRear,
+        AudioSurroundModeNext, AudioTrebleDown, AudioTrebleUp, AudioVolumeDown, AudioVolumeUp,
+        AudioVolumeMute, MicrophoneToggle, MicrophoneVolumeDown, MicrophoneVolumeUp,
+        MicrophoneVolumeMute, SpeechCorrectionList, SpeechInputToggle, LaunchApplication1,
+        LaunchApplication2, LaunchCalendar, LaunchContacts, LaunchMail, LaunchMediaPlayer,
+        LaunchMusicPlayer, LaunchPhone, LaunchScreenSaver, LaunchSpreadsheet, LaunchWebBrowser,
+        LaunchWebCam, LaunchWordProcessor, BrowserBack, BrowserFavorites, BrowserForward,
+        BrowserHome, BrowserRefresh, BrowserSearch, BrowserStop, AppSwitch, Call, Camera,
+        CameraFocus, EndCall, GoBack, GoHome, HeadsetHook, LastNumberRedial, Notification,
+        MannerMode, VoiceDial, TV, TV3DMode, TVAntennaCable, TVAudioDescription,
+        TVAudioDescriptionMixDown, TVAudioDescriptionMixUp, TVContentsMenu, TVDataService, TVInput,
+        TVInputComponent1, TVInputComponent2, TVInputComposite1, TVInputComposite2, TVInputHDMI1,
+        TVInputHDMI2, TVInputHDMI3, TVInputHDMI4, TVInputVGA1, TVMediaContext, TVNetwork,
+        TVNumberEntry, TVPower, TVRadioService, TVSatellite, TVSatelliteBS, TVSatelliteCS,
+        TVSatelliteToggle, TVTerrestrialAnalog, TVTerrestrialDigital, TVTimer, AVRInput, AVRPower,
+        ColorF0Red, ColorF1Green, ColorF2Yellow, ColorF3Blue, ColorF4Grey, ColorF5Brown,
+        ClosedCaptionToggle, Dimmer, DisplaySwap, DVR, Exit, FavoriteClear0, FavoriteClear1,
+        FavoriteClear2, FavoriteClear3, FavoriteRecall0, FavoriteRecall1, FavoriteRecall2,
+        FavoriteRecall3, FavoriteStore0, FavoriteStore1, FavoriteStore2, FavoriteStore3, Guide,
+        GuideNextDay, GuidePreviousDay, Info, InstantReplay, Link, ListProgram, LiveContent, Lock,
+        MediaApps, MediaAudioTrack, MediaLast, MediaSkipBackward, MediaSkipForward,
+        MediaStepBackward, MediaStepForward, MediaTopMenu, NavigateIn, NavigateNext, NavigateOut,
+        NavigatePrevious, NextFavoriteChannel, NextUserProfile, OnDemand, Pairing, PinPDown,
+        PinPMove, PinPToggle, PinPUp, PlaySpeedDown, PlaySpeedReset, PlaySpeedUp, RandomToggle,
+        RcLowBattery, RecordSpeedNext, RfBypass, ScanChannelsToggle, ScreenModeNext, Settings,
+        SplitScreenToggle, STBInput, STBPower, Subtitle, Teletext, VideoModeNext, Wink, ZoomToggle,
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20,
+        F21, F22, F23, F24, F25, F26, F27, F28, F29, F30, F31, F32, F33, F34, F35,
+    ]
+};
+
+/// Everything `WindowState` passes on to a [`State`] apart from rendering, in a form that can be
+/// written to a file and replayed.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum InputEvent {
+    Resized(Vec2),
+    Key(KeyCode, bool),
+    /// Native keys the layout couldn't map are recorded without their platform code.
+    LogicalKey(Key, bool),
+    Modifiers(ModifiersState),
+    Cursor(Vec2),
+    MouseButton(MouseButton, bool),
+    Scroll(Vec2),
+    MouseMotion(Vec2),
+    Text(String),
+    ImePreedit(String, Option<(usize, usize)>),
+    ImeCommit(String),
+    Touch(Touch),
+    Focused(bool),
+    Occluded(bool),
+    ScaleFactor(f32),
+    Moved(IVec2),
+    HoveredFile(PathBuf),
+    HoveredFileCancelled,
+    DroppedFile(PathBuf),
+    Update(Duration),
+}
+
+impl InputEvent {
+    /// Updates the input state of `ctx`, then calls the matching callback of `state`.
+    pub(crate) fn apply<T: State>(self, ctx: &mut Context, state: &mut T) {
+        match self {
+            InputEvent::Resized(size) => {
+                ctx.set_size(size);
+                state.resize(ctx, size, ctx.logical_size());
+            }
+            InputEvent::Key(code, pressed) => {
+                ctx.input_mut().set_key(code, pressed);
+                state.key(ctx, code, pressed);
+            }
+            InputEvent::LogicalKey(key, pressed) => state.logical_key(ctx, &key, pressed),
+            InputEvent::Modifiers(modifiers) => ctx.input_mut().set_modifiers(modifiers),
+            InputEvent::Cursor(position) => {
+                ctx.input_mut().set_cursor(position);
+                state.cursor(ctx, position);
+            }
+            InputEvent::MouseButton(button, pressed) => {
+                ctx.input_mut().set_mouse_button(button, pressed);
+                state.mouse_button(ctx, button, pressed);
+            }
+            InputEvent::Scroll(delta) => {
+                ctx.input_mut().add_scroll(delta);
+                state.scroll(ctx, delta);
+            }
+            InputEvent::MouseMotion(delta) => {
+                ctx.input_mut().add_mouse_motion(delta);
+                state.mouse_motion(ctx, delta);
+            }
+            InputEvent::Text(text) => {
+                ctx.input_mut().add_text(&text);
+                state.text(ctx, &text);
+            }
+            InputEvent::ImePreedit(text, cursor) => state.ime_preedit(ctx, &text, cursor),
+            InputEvent::ImeCommit(text) => {
+                ctx.input_mut().add_text(&text);
+                state.ime_commit(ctx, &text);
+            }
+            InputEvent::Touch(touch) => {
+                let gestures = ctx.input_mut().touch(touch);
+                state.touch(ctx, touch);
+                for gesture in gestures {
+                    state.gesture(ctx, gesture);
+                }
+            }
+            InputEvent::Focused(focused) => {
+                ctx.set_focused(focused);
+                if !focused {
+                    ctx.input_mut().release_all();
+                }
+                state.focused(ctx, focused);
+            }
+            InputEvent::Occluded(occluded) => {
+                ctx.set_occluded(occluded);
+                state.occluded(ctx, occluded);
+            }
+            InputEvent::ScaleFactor(scale_factor) => {
+                ctx.set_scale_factor(scale_factor);
+                state.scale_factor(ctx, scale_factor);
+            }
+            InputEvent::Moved(position) => state.moved(ctx, position),
+            InputEvent::HoveredFile(path) => state.file_hovered(ctx, &path),
+            InputEvent::HoveredFileCancelled => state.file_hover_cancelled(ctx),
+            InputEvent::DroppedFile(path) => state.file_dropped(ctx, &path),
+            InputEvent::Update(delta) => {
+                state.update(ctx, delta);
+                ctx.input_mut().end_frame(delta);
+            }
+        }
+    }
+}
+
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputEvent::Resized(size) => write!(f, "resize {} {}", size.x, size.y),
+            InputEvent::Key(code, pressed) => {
+                write!(f, "key {} {}", Binding::Key(*code), u8::from(*pressed))
+            }
+            InputEvent::LogicalKey(key, pressed) => {
+                write!(f, "logical {} ", u8::from(*pressed))?;
+                match key {
+                    Key::Named(named) => write!(f, "named {named:?}"),
+                    Key::Character(text) => write!(f, "char {}", escape(text)),
+                    Key::Dead(Some(c)) => write!(f, "dead {}", escape(&c.to_string())),
+                    Key::Dead(None) => write!(f, "dead"),
+                    Key::Unidentified(_) => write!(f, "unidentified"),
+                }
+            }
+            InputEvent::Modifiers(modifiers) => write!(f, "modifiers {}", modifiers.bits()),
+            InputEvent::Cursor(position) => write!(f, "cursor {} {}", position.x, position.y),
+            InputEvent::MouseButton(button, pressed) => {
+                write!(
+                    f,
+                    "button {} {}",
+                    Binding::Mouse(*button),
+                    u8::from(*pressed)
+                )
+            }
+            InputEvent::Scroll(delta) => write!(f, "scroll {} {}", delta.x, delta.y),
+            InputEvent::MouseMotion(delta) => write!(f, "motion {} {}", delta.x, delta.y),
+            InputEvent::Text(text) => write!(f, "text {}", escape(text)),
+            InputEvent::ImePreedit(text, Some((start, end))) => {
+                write!(f, "preedit {start} {end} {}", escape(text))
+            }
+            InputEvent::ImePreedit(text, None) => write!(f, "preedit - - {}", escape(text)),
+            InputEvent::ImeCommit(text) => write!(f, "commit {}", escape(text)),
+            InputEvent::Touch(touch) => {
+                let phase = match touch.phase {
+                    TouchPhase::Started => "started",
+                    TouchPhase::Moved => "moved",
+                    TouchPhase::Ended => "ended",
+                    TouchPhase::Cancelled => "cancelled",
+                };
+                write!(
+                    f,
+                    "touch {} {phase} {} {}",
+                    touch.id, touch.position.x, touch.position.y
+                )?;
+                match touch.force {
+                    Some(force) => write!(f, " {force}"),
+                    None => write!(f, " -"),
+                }
+            }
+            InputEvent::Focused(focused) => write!(f, "focus {}", u8::from(*focused)),
+            InputEvent::Occluded(occluded) => write!(f, "occluded {}", u8::from(*occluded)),
+            InputEvent::ScaleFactor(scale_factor) => write!(f, "scale {scale_factor}"),
+            InputEvent::Moved(position) => write!(f, "moved {} {}", position.x, position.y),
+            InputEvent::HoveredFile(path) => {
+                write!(f, "hover {}", escape(&path.to_string_lossy()))
+            }
+            InputEvent::HoveredFileCancelled => write!(f, "unhover"),
+            InputEvent::DroppedFile(path) => write!(f, "drop {}", escape(&path.to_string_lossy())),
+            InputEvent::Update(delta) => write!(f, "update {}", delta.as_nanos()),
+        }
+    }
+}
+
+/// Keeps text on a single line, its spaces intact.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        result.push(match c {
+            '\\' => match chars.next() {
+                Some('n') => '\n',
+                Some('r') => '\r',
+                _ => '\\',
+            },
+            c => c,
+        });
+    }
+    result
+}
+
+fn parse<T: FromStr>(token: Option<&str>) -> Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    token
+        .ok_or("missing value")?
+        .parse()
+        .map_err(|error| format!("{error}"))
+}
+
+impl FromStr for InputEvent {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        if let Some(text) = line.strip_prefix("text ") {
+            return Ok(InputEvent::Text(unescape(text)));
+        }
+        if let Some(text) = line.strip_prefix("commit ") {
+            return Ok(InputEvent::ImeCommit(unescape(text)));
+        }
+        if let Some(path) = line.strip_prefix("hover ") {
+            return Ok(InputEvent::HoveredFile(unescape(path).into()));
+        }
+        if let Some(path) = line.strip_prefix("drop ") {
+            return Ok(InputEvent::DroppedFile(unescape(path).into()));
+        }
+        if let Some(rest) = line.strip_prefix("logical ") {
+            let mut parts = rest.splitn(3, ' ');
+            let pressed = parse::<u8>(parts.next())? == 1;
+            let kind = parts.next();
+            let value = parts.next();
+            let key = match (kind, value) {
+                (Some("named"), Some(name)) => NAMED_KEYS
+                    .iter()
+                    .find(|named| format!("{named:?}") == name)
+                    .map(|&named| Key::Named(named))
+                    .ok_or_else(|| format!("unknown named key {name:?}"))?,
+                (Some("char"), Some(text)) => Key::Character(unescape(text).into()),
+                (Some("dead"), Some(text)) => Key::Dead(unescape(text).chars().next()),
+                (Some("dead"), None) => Key::Dead(None),
+                (Some("unidentified"), None) => Key::Unidentified(NativeKey::Unidentified),
+                _ => return Err(format!("invalid logical key {rest:?}")),
+            };
+            return Ok(InputEvent::LogicalKey(key, pressed));
+        }
+        if let Some(rest) = line.strip_prefix("preedit ") {
+            let mut parts = rest.splitn(3, ' ');
+            let (start, end) = (parts.next(), parts.next());
+            let text = unescape(parts.next().ok_or("preedit is missing its text")?);
+            let cursor = match (start, end) {
+                (Some("-"), Some("-")) => None,
+                _ => Some((parse(start)?, parse(end)?)),
+            };
+            return Ok(InputEvent::ImePreedit(text, cursor));
+        }
+        let mut tokens = line.split_whitespace();
+        let kind = tokens.next().ok_or("empty line")?;
+        let mut vec2 = || -> Result<Vec2, String> {
+            Ok(Vec2::new(parse(tokens.next())?, parse(tokens.next())?))
+        };
+        let event = match kind {
+            "resize" => InputEvent::Resized(vec2()?),
+            "cursor" => InputEvent::Cursor(vec2()?),
+            "scroll" => InputEvent::Scroll(vec2()?),
+            "motion" => InputEvent::MouseMotion(vec2()?),
+            "key" | "button" => {
+                let binding = parse(tokens.next())?;
+                let pressed = parse::<u8>(tokens.next())? == 1;
+                match binding {
+                    Binding::Key(code) if kind == "key" => InputEvent::Key(code, pressed),
+                    Binding::Mouse(button) if kind == "button" => {
+                        InputEvent::MouseButton(button, pressed)
+                    }
+                    _ => return Err(format!("{binding} is not a {kind}")),
+                }
+            }
+            "modifiers" => {
+                InputEvent::Modifiers(ModifiersState::from_bits_truncate(parse(tokens.next())?))
+            }
+            "touch" => {
+                let id = parse(tokens.next())?;
+                let phase = match tokens.next() {
+                    Some("started") => TouchPhase::Started,
+                    Some("moved") => TouchPhase::Moved,
+                    Some("ended") => TouchPhase::Ended,
+                    Some("cancelled") => TouchPhase::Cancelled,
+                    phase => return Err(format!("unknown touch phase {phase:?}")),
+                };
+                let position = Vec2::new(parse(tokens.next())?, parse(tokens.next())?);
+                let force = match tokens.next() {
+                    Some("-") => None,
+                    force => Some(parse(force)?),
+                };
+                InputEvent::Touch(Touch {
+                    id,
+                    phase,
+                    position,
+                    force,
+                })
+            }
+            "focus" => InputEvent::Focused(parse::<u8>(tokens.next())? == 1),
+            "occluded" => InputEvent::Occluded(parse::<u8>(tokens.next())? == 1),
+            "scale" => InputEvent::ScaleFactor(parse(tokens.next())?),
+            "moved" => InputEvent::Moved(IVec2::new(parse(tokens.next())?, parse(tokens.next())?)),
+            "unhover" => InputEvent::HoveredFileCancelled,
+            "update" => InputEvent::Update(Duration::from_nanos(parse(tokens.next())?)),
+            _ => return Err(format!("unknown event {kind:?}")),
+        };
+        Ok(event)
+    }
+}
+
+/// Why a recording can't be replayed.
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    /// A line of the recording that isn't an input event, counted from 1.
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// There is no GPU adapter to create the headless context with.
+    NoAdapter,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "can't read the recording: {error}"),
+            ReplayError::Parse { line, message } => {
+                write!(f, "line {line} of the recording: {message}")
+            }
+            ReplayError::NoAdapter => write!(f, "no GPU adapter is available"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+/// Writes input events to the recording file as they are delivered, one per line. The file is
+/// flushed every update, so a crash loses at most the events of the update it happens in.
+pub(crate) struct RecordingWriter {
+    file: BufWriter<File>,
+}
+
+impl RecordingWriter {
+    pub(crate) fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            file: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    pub(crate) fn push(&mut self, event: &InputEvent) -> io::Result<()> {
+        writeln!(self.file, "{event}")
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Input events read from a file written by [`RecordingWriter`], in the order they were
+/// delivered.
+pub(crate) struct Recording {
+    events: VecDeque<InputEvent>,
+}
+
+impl Recording {
+    pub(crate) fn load(path: &Path) -> Result<Self, ReplayError> {
+        let text = std::fs::read_to_string(path)?;
+        let events = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                line.parse().map_err(|message| ReplayError::Parse {
+                    line: index + 1,
+                    message,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { events })
+    }
+
+    /// Size of the window when recording started.
+    pub(crate) fn initial_size(&self) -> Option<Vec2> {
+        self.events
+            .iter()
+            .take_while(|event| !matches!(event, InputEvent::Update(_)))
+            .find_map(|event| match event {
+                InputEvent::Resized(size) => Some(*size),
+                _ => None,
+            })
+    }
+
+    /// Applies events up to and including the next update, returning false once all of them
+    /// have been replayed.
+    pub(crate) fn replay_update<T: State>(&mut self, ctx: &mut Context, state: &mut T) -> bool {
+        while let Some(event) = self.events.pop_front() {
+            let update = matches!(event, InputEvent::Update(_));
+            event.apply(ctx, state);
+            if update {
+                return true;
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,321 @@
+use glam::*;
+
+use crate::{frame::Target, BindGroupEntry, Context, DepthFormat, Texture2D, TextureFormat};
+
+/// Offscreen color texture with an optional depth buffer, rendered to with
+/// [`Frame::target_pass`](crate::Frame::target_pass) and sampled through [`RenderTarget::texture`]
+/// and [`RenderTarget::depth_texture`].
+pub struct RenderTarget {
+    texture: Texture2D,
+    depth: Option<DepthTexture>,
+}
+
+impl RenderTarget {
+    pub fn new(
+        ctx: &Context,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        depth_format: Option<DepthFormat>,
+    ) -> Self {
+        let texture = Texture2D::create(
+            ctx,
+            format,
+            width,
+            height,
+            1,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        );
+        let depth = depth_format.map(|format| DepthTexture::new(ctx, format, width, height));
+        Self { texture, depth }
+    }
+
+    /// Recreates the target with a new size, dropping its contents.
+    pub fn resize(&mut self, ctx: &Context, width: u32, height: u32) {
+        *self = Self::new(ctx, width, height, self.format(), self.depth_format());
+    }
+
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    /// The depth buffer, to sample e.g. as a shadow map once drawn.
+    pub fn depth_texture(&self) -> Option<&DepthTexture> {
+        self.depth.as_ref()
+    }
+
+    pub fn size(&self) -> Vec2 {
+        vec2(self.texture.width() as f32, self.texture.height() as f32)
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.texture.format()
+    }
+
+    pub fn depth_format(&self) -> Option<DepthFormat> {
+        self.depth.as_ref().map(DepthTexture::format)
+    }
+
+    pub(crate) fn view(&self) -> &wgpu::TextureView {
+        self.texture.view()
+    }
+
+    pub(crate) fn depth_stencil(&self) -> Option<&wgpu::TextureView> {
+        self.depth.as_ref().map(|depth| &depth.attachment)
+    }
+
+    pub(crate) fn target(&self) -> Target {
+        Target {
+            format: self.format().into(),
+            depth_format: self.depth_format(),
+            sample_count: 1,
+        }
+    }
+}
+
+/// Depth buffer of a [`RenderTarget`], bound as a `texture_depth_2d` to sample with
+/// `textureSampleCompare` and a sampler made by
+/// [`SamplerBuilder::with_compare`](crate::SamplerBuilder::with_compare). Stencil formats are
+/// sampled for their depth.
+pub struct DepthTexture {
+    texture: wgpu::Texture,
+    attachment: wgpu::TextureView,
+    view: wgpu::TextureView,
+    format: DepthFormat,
+}
+
+impl DepthTexture {
+    fn new(ctx: &Context, format: DepthFormat, width: u32, height: u32) -> Self {
+        let texture = ctx.device().create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: format.into(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let attachment = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            aspect: wgpu::TextureAspect::DepthOnly,
+            ..Default::default()
+        });
+        Self {
+            texture,
+            attachment,
+            view,
+            format,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.height()
+    }
+
+    pub fn format(&self) -> DepthFormat {
+        self.format
+    }
+}
+
+impl BindGroupEntry for DepthTexture {
+    fn visibility(&self) -> wgpu::ShaderStages {
+        wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE
+    }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        }
+    }
+
+    fn resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::TextureView(&self.view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BindGroupBuilder, BindGroupBuilderWith, BlendState, Clear, CompareFunction, Pass,
+        PipelineBuilder, SamplerBuilder, Shader, Viewport,
+    };
+
+    const VERTEX_SHADER: &str = "
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.5, 1.0);
+}
+";
+
+    const UINT_SHADER: &str = "
+@fragment
+fn fs_main() -> @location(0) u32 {
+    return 9u;
+}
+";
+
+    const COLOR_SHADER: &str = "
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
+";
+
+    const SHADOW_SHADER: &str = "
+@group(0) @binding(0) var depth: texture_depth_2d;
+@group(0) @binding(1) var shadow: sampler_comparison;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = position.xy / vec2<f32>(textureDimensions(depth));
+    return vec4<f32>(textureSampleCompare(depth, shadow, uv, 0.75));
+}
+";
+
+    fn headless() -> Option<Context> {
+        let ctx = pollster::block_on(Context::headless(Vec2::ONE));
+        if ctx.is_none() {
+            eprintln!("skipping, there is no GPU adapter");
+        }
+        ctx
+    }
+
+    fn shader(ctx: &Context, fragment: &str) -> Shader {
+        Shader::new(ctx, format!("{VERTEX_SHADER}{fragment}"))
+    }
+
+    fn pass<'a>(
+        ctx: &'a Context,
+        encoder: &'a mut wgpu::CommandEncoder,
+        target: &'a RenderTarget,
+    ) -> Pass<'a> {
+        Pass::new(
+            ctx,
+            encoder,
+            target.view(),
+            target.depth_stencil(),
+            target.target(),
+            Clear::new(1.0, 0.0, 0.0, 0.0),
+        )
+    }
+
+    /// Red channel of each pixel.
+    fn read(ctx: &Context, target: &RenderTarget) -> Vec<f32> {
+        let image = target.texture().to_rgba32f_image(ctx).unwrap();
+        image.pixels().map(|pixel| pixel[0]).collect()
+    }
+
+    /// Clears the target to 1, the left half to 7 with a viewport and draws 9 into the right
+    /// half if the shader is given.
+    fn clear_halves(ctx: &Context, format: TextureFormat, shader: Option<&Shader>) -> Vec<f32> {
+        let target = RenderTarget::new(ctx, 4, 2, format, Some(DepthFormat::Depth24Stencil8));
+        let pipeline = shader.map(|shader| {
+            PipelineBuilder::new(shader)
+                .with_blend(BlendState::ALPHA_BLENDING)
+                .with_cull_mode(None)
+                .build(ctx)
+        });
+        let mut encoder = ctx
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = pass(ctx, &mut encoder, &target);
+            let half = vec2(0.5, 1.0);
+            pass.restrict(
+                &Viewport::new(Vec2::ZERO, half).with_clear(7.0, 0.0, 0.0, 0.0),
+                target.size(),
+            );
+            pass.restrict(&Viewport::new(vec2(0.5, 0.0), half), target.size());
+            if let Some(pipeline) = &pipeline {
+                pipeline.attach(&mut pass);
+                pass.draw(0..3, 0..1);
+            }
+        }
+        ctx.queue().submit(std::iter::once(encoder.finish()));
+        read(ctx, &target)
+    }
+
+    #[test]
+    fn non_blendable_targets() {
+        let Some(ctx) = headless() else {
+            return;
+        };
+        let shader = shader(&ctx, UINT_SHADER);
+        assert_eq!(
+            clear_halves(&ctx, TextureFormat::R32Uint, Some(&shader)),
+            [7.0, 7.0, 9.0, 9.0, 7.0, 7.0, 9.0, 9.0]
+        );
+        assert_eq!(
+            clear_halves(&ctx, TextureFormat::Rgba32Float, None),
+            [7.0, 7.0, 1.0, 1.0, 7.0, 7.0, 1.0, 1.0]
+        );
+    }
+
+    /// Draws depth 0.5 into the right half of a shadow map cleared to 1, then compares 0.75
+    /// against it.
+    #[test]
+    fn sample_depth_with_comparison() {
+        let Some(ctx) = headless() else {
+            return;
+        };
+        let shadow_map = RenderTarget::new(
+            &ctx,
+            4,
+            2,
+            TextureFormat::Rgba8Unorm,
+            Some(DepthFormat::Depth24Stencil8),
+        );
+        let output = RenderTarget::new(&ctx, 4, 2, TextureFormat::R32Float, None);
+        let depth_shader = shader(&ctx, COLOR_SHADER);
+        let depth_pipeline = PipelineBuilder::new(&depth_shader)
+            .with_depth(CompareFunction::Always, true)
+            .with_cull_mode(None)
+            .build(&ctx);
+        let sampler = SamplerBuilder::new()
+            .with_compare(CompareFunction::Less)
+            .build(&ctx);
+        let shadow_shader = shader(&ctx, SHADOW_SHADER);
+        let mut builder = PipelineBuilder::new(&shadow_shader).with_cull_mode(None);
+        let bind_group = BindGroupBuilder::new()
+            .with(shadow_map.depth_texture().unwrap())
+            .with(&sampler)
+            .build(&ctx, &mut builder);
+        let shadow_pipeline = builder.build(&ctx);
+        let mut encoder = ctx
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = pass(&ctx, &mut encoder, &shadow_map);
+            pass.restrict(
+                &Viewport::new(vec2(0.5, 0.0), vec2(0.5, 1.0)),
+                shadow_map.size(),
+            );
+            depth_pipeline.attach(&mut pass);
+            pass.draw(0..3, 0..1);
+        }
+        {
+            let mut pass = pass(&ctx, &mut encoder, &output);
+            shadow_pipeline.attach(&mut pass);
+            bind_group.attach(&mut pass, 0, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        ctx.queue().submit(std::iter::once(encoder.finish()));
+        assert_eq!(
+            read(&ctx, &output),
+            [1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0]
+        );
+    }
+}
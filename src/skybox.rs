@@ -0,0 +1,82 @@
+use glam::*;
+
+use crate::TextureCube;
+use crate::{BindGroup, BindGroupBuilder, BindGroupBuilderWith, CompareFunction, Context, Pass};
+use crate::{FilterMode, Pipeline, PipelineBuilder, SamplerBuilder, Shader, ShaderStages};
+
+const SHADER: &str = "
+struct Sky {
+    inverse_view_proj: mat4x4<f32>,
+};
+
+var<push_constant> sky: Sky;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.ndc = uv * 2.0 - 1.0;
+    out.position = vec4<f32>(out.ndc, 1.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var cube: texture_cube<f32>;
+@group(0) @binding(1)
+var cube_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let world = sky.inverse_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    return textureSample(cube, cube_sampler, world.xyz / world.w);
+}
+";
+
+crate::impl_uniform! {
+    struct SkyUniform {
+        inverse_view_proj: [[f32; 4]; 4],
+    }
+}
+
+/// Draws a cube texture behind everything else, on pixels still at the far depth.
+pub struct Skybox {
+    pipeline: Pipeline,
+    bind_group: BindGroup,
+}
+
+impl Skybox {
+    pub fn new(ctx: &Context, texture: &TextureCube) -> Self {
+        let shader = Shader::new(ctx, SHADER.to_string());
+        let mut pipeline_builder = PipelineBuilder::new(&shader)
+            .with_depth(CompareFunction::LessEqual, false)
+            .with_push_constants::<SkyUniform>(ShaderStages::FRAGMENT);
+        let sampler = SamplerBuilder::new()
+            .with_filter(FilterMode::Linear)
+            .build(ctx);
+        let bind_group = BindGroupBuilder::new()
+            .with(texture)
+            .with(&sampler)
+            .build(ctx, &mut pipeline_builder);
+        Self {
+            pipeline: pipeline_builder.build(ctx),
+            bind_group,
+        }
+    }
+
+    /// Only the rotation of `view` is used, the sky stays put as the camera moves.
+    pub fn draw<'a>(&'a self, pass: &mut Pass<'a>, view: Mat4, projection: Mat4) {
+        let rotation = Mat4::from_mat3(Mat3::from_mat4(view));
+        let uniform = SkyUniform {
+            inverse_view_proj: (projection * rotation).inverse().to_cols_array_2d(),
+        };
+        self.pipeline.attach(pass);
+        self.bind_group.attach(pass, 0, &[]);
+        pass.set_push_constants(&uniform);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -1,95 +1,1609 @@
-use image::RgbaImage;
+use glam::*;
+use std::{fmt, path::Path};
 
-use crate::{BindGroupEntry, Context};
+use image::{DynamicImage, GenericImageView, ImageError, ImageFormat, Rgba32FImage, RgbaImage};
 
-pub struct Texture2D(wgpu::TextureView);
+use crate::{
+    compressed::{CompressedImage, CompressedTextureError},
+    mipmap::{self, MipmapGenerator},
+    AddressMode, BindGroupEntry, CompareFunction, Context, FilterMode, SamplerBorderColor,
+    StorageAccess,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    Rgba8UnormSrgb,
+    Rgba8Unorm,
+    R8Unorm,
+    Rg8Unorm,
+    R16Float,
+    Rgba16Float,
+    Rgba32Float,
+    R32Float,
+    R32Uint,
+    /// Block compressed format of a KTX2 or DDS file, uploaded as is.
+    Compressed(wgpu::TextureFormat),
+}
+
+impl From<TextureFormat> for wgpu::TextureFormat {
+    fn from(format: TextureFormat) -> Self {
+        match format {
+            TextureFormat::Rgba8UnormSrgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            TextureFormat::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
+            TextureFormat::R8Unorm => wgpu::TextureFormat::R8Unorm,
+            TextureFormat::Rg8Unorm => wgpu::TextureFormat::Rg8Unorm,
+            TextureFormat::R16Float => wgpu::TextureFormat::R16Float,
+            TextureFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+            TextureFormat::Rgba32Float => wgpu::TextureFormat::Rgba32Float,
+            TextureFormat::R32Float => wgpu::TextureFormat::R32Float,
+            TextureFormat::R32Uint => wgpu::TextureFormat::R32Uint,
+            TextureFormat::Compressed(format) => format,
+        }
+    }
+}
+
+/// Format of a depth buffer, the `Stencil8` variants adding an 8 bit stencil buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DepthFormat {
+    Depth16,
+    Depth24,
+    Depth24Stencil8,
+    Depth32,
+    /// Requires `Features::DEPTH32FLOAT_STENCIL8`.
+    Depth32Stencil8,
+}
+
+impl From<DepthFormat> for wgpu::TextureFormat {
+    fn from(format: DepthFormat) -> Self {
+        match format {
+            DepthFormat::Depth16 => wgpu::TextureFormat::Depth16Unorm,
+            DepthFormat::Depth24 => wgpu::TextureFormat::Depth24Plus,
+            DepthFormat::Depth24Stencil8 => wgpu::TextureFormat::Depth24PlusStencil8,
+            DepthFormat::Depth32 => wgpu::TextureFormat::Depth32Float,
+            DepthFormat::Depth32Stencil8 => wgpu::TextureFormat::Depth32FloatStencil8,
+        }
+    }
+}
+
+impl DepthFormat {
+    pub(crate) fn has_stencil(self) -> bool {
+        matches!(
+            self,
+            DepthFormat::Depth24Stencil8 | DepthFormat::Depth32Stencil8
+        )
+    }
+}
+
+impl TextureFormat {
+    pub(crate) fn sample_type(self) -> wgpu::TextureSampleType {
+        match self {
+            TextureFormat::Rgba32Float | TextureFormat::R32Float => {
+                wgpu::TextureSampleType::Float { filterable: false }
+            }
+            TextureFormat::R32Uint => wgpu::TextureSampleType::Uint,
+            _ => wgpu::TextureSampleType::Float { filterable: true },
+        }
+    }
+
+    fn is_integer(self) -> bool {
+        self == TextureFormat::R32Uint
+    }
+
+    /// Bytes per block for compressed formats.
+    pub(crate) fn bytes_per_pixel(self) -> u32 {
+        wgpu::TextureFormat::from(self)
+            .block_copy_size(None)
+            .unwrap()
+    }
+
+    /// Converts normalized or float pixels, keeping only the channels the format has.
+    fn encode(self, image: &Rgba32FImage) -> Vec<u8> {
+        let channels = match self {
+            TextureFormat::R8Unorm
+            | TextureFormat::R16Float
+            | TextureFormat::R32Float
+            | TextureFormat::R32Uint => 1,
+            TextureFormat::Rg8Unorm => 2,
+            _ => 4,
+        };
+        let values = image
+            .pixels()
+            .flat_map(|pixel| pixel.0.into_iter().take(channels));
+        match self {
+            TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Rgba8Unorm
+            | TextureFormat::R8Unorm
+            | TextureFormat::Rg8Unorm => values
+                .map(|value| (value * 255.0).round().clamp(0.0, 255.0) as u8)
+                .collect(),
+            TextureFormat::R16Float | TextureFormat::Rgba16Float => values
+                .flat_map(|value| f32_to_f16(value).to_le_bytes())
+                .collect(),
+            TextureFormat::Rgba32Float | TextureFormat::R32Float => {
+                values.flat_map(f32::to_le_bytes).collect()
+            }
+            TextureFormat::R32Uint => values
+                .flat_map(|value| (value as u32).to_le_bytes())
+                .collect(),
+            TextureFormat::Compressed(format) => panic!("can't encode pixels to {format:?}"),
+        }
+    }
+
+    /// Inverse of [`TextureFormat::encode`], missing channels are zero with an opaque alpha.
+    /// Compressed formats need to be decompressed first.
+    fn decode(self, width: u32, height: u32, data: &[u8]) -> Option<Rgba32FImage> {
+        let values: Vec<f32> = match self {
+            TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Rgba8Unorm
+            | TextureFormat::R8Unorm
+            | TextureFormat::Rg8Unorm => data.iter().map(|&value| value as f32 / 255.0).collect(),
+            TextureFormat::R16Float | TextureFormat::Rgba16Float => data
+                .chunks_exact(2)
+                .map(|bytes| f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])))
+                .collect(),
+            TextureFormat::Rgba32Float | TextureFormat::R32Float => {
+                bytemuck::pod_collect_to_vec(data)
+            }
+            TextureFormat::R32Uint => bytemuck::pod_collect_to_vec::<u8, u32>(data)
+                .into_iter()
+                .map(|value| value as f32)
+                .collect(),
+            TextureFormat::Compressed(_) => return None,
+        };
+        let channels = values.len() / (width * height) as usize;
+        let mut values = values.chunks_exact(channels).map(|pixel| {
+            image::Rgba(std::array::from_fn(|channel| {
+                pixel
+                    .get(channel)
+                    .copied()
+                    .unwrap_or(if channel == 3 { 1.0 } else { 0.0 })
+            }))
+        });
+        Some(Rgba32FImage::from_fn(width, height, |_, _| {
+            values.next().unwrap()
+        }))
+    }
+
+    /// Integer formats take the red channel of 8 and 16 bit images as stored, without
+    /// normalization.
+    fn encode_integer(image: &DynamicImage) -> Vec<u8> {
+        image
+            .to_rgba16()
+            .pixels()
+            .flat_map(|pixel| {
+                let value = match image.color().bytes_per_pixel() / image.color().channel_count() {
+                    1 => pixel[0] as u32 >> 8,
+                    _ => pixel[0] as u32,
+                };
+                value.to_le_bytes()
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = 14 - exponent;
+        let half = (mantissa >> shift) as u16;
+        let round = ((mantissa >> (shift - 1)) & 1) as u16;
+        sign | (half + round)
+    } else {
+        let half = sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16;
+        half + ((mantissa >> 12) & 1) as u16
+    }
+}
+
+pub(crate) fn f16_to_f32(value: u16) -> f32 {
+    let sign = ((value & 0x8000) as u32) << 16;
+    let exponent = ((value >> 10) & 0x1f) as u32;
+    let mantissa = (value & 0x3ff) as u32;
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            let shift = mantissa.leading_zeros() - 21;
+            sign | ((113 - shift) << 23) | ((mantissa << shift) & 0x3ff) << 13
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+pub(crate) fn create_texture(
+    ctx: &Context,
+    format: TextureFormat,
+    size: wgpu::Extent3d,
+    dimension: wgpu::TextureDimension,
+    mip_level_count: u32,
+    usage: wgpu::TextureUsages,
+) -> wgpu::Texture {
+    ctx.device().create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size,
+        mip_level_count,
+        sample_count: 1,
+        dimension,
+        format: format.into(),
+        usage: wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::COPY_SRC
+            | usage,
+        view_formats: &[],
+    })
+}
+
+/// Writes a whole mip level, every layer one after the other.
+pub(crate) fn write_texture_level(
+    ctx: &Context,
+    texture: &wgpu::Texture,
+    format: TextureFormat,
+    level: u32,
+    data: &[u8],
+) {
+    let size = texture.size().mip_level_size(level, texture.dimension());
+    let format = wgpu::TextureFormat::from(format);
+    let (block_width, block_height) = format.block_dimensions();
+    ctx.queue().write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(
+                size.width.div_ceil(block_width) * format.block_copy_size(None).unwrap(),
+            ),
+            rows_per_image: Some(size.height.div_ceil(block_height)),
+        },
+        size.physical_size(format),
+    );
+}
+
+/// Copies a whole mip level back to the CPU, blocking until the GPU is done. The rows are
+/// returned tightly packed like [`write_texture_level`] expects them.
+pub(crate) fn read_texture_level(
+    ctx: &Context,
+    texture: &wgpu::Texture,
+    format: TextureFormat,
+    level: u32,
+) -> Result<Vec<u8>, wgpu::BufferAsyncError> {
+    let size = texture.size().mip_level_size(level, texture.dimension());
+    let format = wgpu::TextureFormat::from(format);
+    let (block_width, block_height) = format.block_dimensions();
+    let row_size = size.width.div_ceil(block_width) * format.block_copy_size(None).unwrap();
+    let rows = size.height.div_ceil(block_height);
+    let padded_row_size = row_size.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let buffer = ctx.device().create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (padded_row_size * rows * size.depth_or_array_layers) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = ctx
+        .device()
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_size),
+                rows_per_image: Some(rows),
+            },
+        },
+        size.physical_size(format),
+    );
+    ctx.queue().submit(std::iter::once(encoder.finish()));
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        sender.send(result).ok();
+    });
+    ctx.device().poll(wgpu::Maintain::Wait).panic_on_timeout();
+    receiver.recv().map_err(|_| wgpu::BufferAsyncError)??;
+    let data = slice
+        .get_mapped_range()
+        .chunks_exact(padded_row_size as usize)
+        .flat_map(|row| &row[..row_size as usize])
+        .copied()
+        .collect();
+    buffer.unmap();
+    Ok(data)
+}
+
+/// Why a texture can't be read back or saved.
+#[derive(Debug)]
+pub enum ReadbackError {
+    /// Mapping the copy of the texture failed, e.g. because the device was lost.
+    Map(wgpu::BufferAsyncError),
+    /// The CPU can't decode the format, e.g. ASTC.
+    UnsupportedFormat(wgpu::TextureFormat),
+    /// Encoding or writing the image file failed.
+    Image(ImageError),
+}
+
+impl fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Map(error) => write!(f, "can't read the texture back: {error}"),
+            Self::UnsupportedFormat(format) => write!(f, "{format:?} can't be decoded"),
+            Self::Image(error) => write!(f, "can't save the texture: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadbackError {}
+
+impl From<wgpu::BufferAsyncError> for ReadbackError {
+    fn from(error: wgpu::BufferAsyncError) -> Self {
+        Self::Map(error)
+    }
+}
+
+impl From<ImageError> for ReadbackError {
+    fn from(error: ImageError) -> Self {
+        Self::Image(error)
+    }
+}
+
+/// Fills every level after the first, on the GPU when the texture was created renderable and
+/// from the layers `layers` returns otherwise.
+pub(crate) fn fill_mip_levels(
+    ctx: &Context,
+    texture: &wgpu::Texture,
+    format: TextureFormat,
+    layers: impl FnOnce() -> Vec<Rgba32FImage>,
+) {
+    if texture.mip_level_count() == 1 {
+        return;
+    }
+    if texture
+        .usage()
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    {
+        ctx.mipmap_generator()
+            .generate(ctx.device(), ctx.queue(), texture);
+    } else {
+        let srgb = wgpu::TextureFormat::from(format).is_srgb();
+        let mut layers = layers();
+        for level in 1..texture.mip_level_count() {
+            layers = layers
+                .iter()
+                .map(|layer| mipmap::downsample(layer, srgb))
+                .collect();
+            let data = layers
+                .iter()
+                .flat_map(|layer| format.encode(layer))
+                .collect::<Vec<_>>();
+            write_texture_level(ctx, texture, format, level, &data);
+        }
+    }
+}
+
+/// Direction through a point of a cube face, `s` and `t` going from -1 to 1 left to right and
+/// top to bottom.
+fn cube_direction(face: u32, s: f32, t: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -t, -s],
+        1 => [-1.0, -t, s],
+        2 => [s, 1.0, t],
+        3 => [s, -1.0, -t],
+        4 => [s, -t, 1.0],
+        _ => [-s, -t, -1.0],
+    }
+}
+
+fn sample_equirectangular(panorama: &Rgba32FImage, [x, y, z]: [f32; 3]) -> image::Rgba<f32> {
+    use std::f32::consts::PI;
+    let length = (x * x + y * y + z * z).sqrt();
+    let u = 0.5 + x.atan2(-z) / (2.0 * PI);
+    let v = (y / length).clamp(-1.0, 1.0).acos() / PI;
+    let (width, height) = panorama.dimensions();
+    let x = (u * width as f32 - 0.5).rem_euclid(width as f32);
+    let y = (v * height as f32 - 0.5).clamp(0.0, height as f32 - 1.0);
+    let (x0, y0) = (x.floor() as u32 % width, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1) % width, (y0 + 1).min(height - 1));
+    let (fx, fy) = (x.fract(), y.fract());
+    let [a, b, c, d] =
+        [(x0, y0), (x1, y0), (x0, y1), (x1, y1)].map(|(x, y)| panorama.get_pixel(x, y).0);
+    image::Rgba(std::array::from_fn(|channel| {
+        let top = a[channel] + (b[channel] - a[channel]) * fx;
+        let bottom = c[channel] + (d[channel] - c[channel]) * fx;
+        top + (bottom - top) * fy
+    }))
+}
+
+pub struct Texture2DBuilder {
+    format: TextureFormat,
+    mipmaps: bool,
+}
+
+impl Default for Texture2DBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Texture2DBuilder {
+    pub fn new() -> Self {
+        Self {
+            format: TextureFormat::Rgba8UnormSrgb,
+            mipmaps: true,
+        }
+    }
+
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Integer formats never get mipmaps, averaging them makes no sense.
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// Decodes an image file in any format `image` supports, including `.hdr` and `.exr`.
+    pub fn build(self, ctx: &Context, data: &[u8]) -> Texture2D {
+        self.build_from_image(ctx, image::load_from_memory(data).unwrap())
+    }
+
+    pub fn build_from_image(self, ctx: &Context, image: DynamicImage) -> Texture2D {
+        let (width, height) = image.dimensions();
+        let format = self.format;
+        let texture = self.build_empty(ctx, width, height);
+        if format.is_integer() {
+            texture.write_level(ctx, 0, &TextureFormat::encode_integer(&image));
+        } else {
+            let image = image.into_rgba32f();
+            texture.write_level(ctx, 0, &format.encode(&image));
+            texture.fill_mip_levels(ctx, || image);
+        }
+        texture
+    }
+
+    /// Uses tightly packed pixels already in the builder's format.
+    pub fn build_from_pixels(
+        self,
+        ctx: &Context,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> Texture2D {
+        assert_eq!(
+            data.len(),
+            (width * height * self.format.bytes_per_pixel()) as usize,
+            "pixel data does not match a {width}x{height} {:?} texture",
+            self.format
+        );
+        let format = self.format;
+        let texture = self.build_empty(ctx, width, height);
+        texture.write_level(ctx, 0, data);
+        texture.fill_mip_levels(ctx, || {
+            format
+                .decode(width, height, data)
+                .expect("compressed mip levels can't be generated, use build_from_mip_levels")
+        });
+        texture
+    }
+
+    /// Creates a zeroed texture to be filled with [`Texture2D::write_region`].
+    pub fn build_empty(self, ctx: &Context, width: u32, height: u32) -> Texture2D {
+        let (mip_level_count, usage) = self.levels(ctx, width, height);
+        Texture2D::create(ctx, self.format, width, height, mip_level_count, usage)
+    }
+
+    /// Builds an array with one layer per image, all of the same size.
+    pub fn build_array(self, ctx: &Context, images: Vec<DynamicImage>) -> Texture2DArray {
+        assert!(!images.is_empty(), "an array needs at least one layer");
+        let (width, height) = images[0].dimensions();
+        assert!(
+            images
+                .iter()
+                .all(|image| image.dimensions() == (width, height)),
+            "array layers differ in size"
+        );
+        let (mip_level_count, usage) = self.levels(ctx, width, height);
+        let texture = Texture2DArray::create(
+            ctx,
+            self.format,
+            width,
+            height,
+            images.len() as u32,
+            mip_level_count,
+            usage,
+        );
+        self.write_layers(ctx, &texture.texture, images);
+        texture
+    }
+
+    /// Slices an atlas into `tile_width` x `tile_height` tiles, one layer each, row by row.
+    pub fn build_array_from_atlas(
+        self,
+        ctx: &Context,
+        atlas: DynamicImage,
+        tile_width: u32,
+        tile_height: u32,
+    ) -> Texture2DArray {
+        assert!(
+            tile_width > 0
+                && tile_height > 0
+                && tile_width <= atlas.width()
+                && tile_height <= atlas.height(),
+            "tiles of {tile_width}x{tile_height} don't fit into a {}x{} atlas",
+            atlas.width(),
+            atlas.height()
+        );
+        let columns = atlas.width() / tile_width;
+        let rows = atlas.height() / tile_height;
+        let tiles = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                atlas.crop_imm(
+                    column * tile_width,
+                    row * tile_height,
+                    tile_width,
+                    tile_height,
+                )
+            })
+            .collect();
+        self.build_array(ctx, tiles)
+    }
+
+    /// Builds a cube from six square faces in `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z` order.
+    pub fn build_cube(self, ctx: &Context, faces: Vec<DynamicImage>) -> TextureCube {
+        assert_eq!(faces.len(), 6, "a cube needs six faces");
+        let size = faces[0].width();
+        assert!(
+            faces.iter().all(|face| face.dimensions() == (size, size)),
+            "cube faces must be square and of the same size"
+        );
+        let (mip_level_count, usage) = self.levels(ctx, size, size);
+        let texture = TextureCube::create(ctx, self.format, size, mip_level_count, usage);
+        self.write_layers(ctx, &texture.texture, faces);
+        texture
+    }
+
+    /// Cuts the faces out of a horizontal (4x3 faces) or vertical (3x4 faces) cross, with `+Z`
+    /// in the middle of it.
+    pub fn build_cube_from_cross(self, ctx: &Context, cross: DynamicImage) -> TextureCube {
+        let horizontal = cross.width() > cross.height();
+        let size = if horizontal {
+            cross.width() / 4
+        } else {
+            cross.width() / 3
+        };
+        let face = |column: u32, row: u32| cross.crop_imm(column * size, row * size, size, size);
+        let faces = if horizontal {
+            vec![
+                face(2, 1),
+                face(0, 1),
+                face(1, 0),
+                face(1, 2),
+                face(1, 1),
+                face(3, 1),
+            ]
+        } else {
+            vec![
+                face(2, 1),
+                face(0, 1),
+                face(1, 0),
+                face(1, 2),
+                face(1, 1),
+                face(1, 3).rotate180(),
+            ]
+        };
+        self.build_cube(ctx, faces)
+    }
+
+    /// Projects an equirectangular panorama onto faces of `size` pixels, `-Z` looking at the
+    /// center of the panorama.
+    pub fn build_cube_from_equirectangular(
+        self,
+        ctx: &Context,
+        panorama: DynamicImage,
+        size: u32,
+    ) -> TextureCube {
+        let panorama = panorama.into_rgba32f();
+        let faces = (0..6)
+            .map(|face| {
+                DynamicImage::ImageRgba32F(Rgba32FImage::from_fn(size, size, |x, y| {
+                    let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+                    let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+                    sample_equirectangular(&panorama, cube_direction(face, s, t))
+                }))
+            })
+            .collect();
+        self.build_cube(ctx, faces)
+    }
+
+    /// Stacks equally sized slices along the depth of a volume. 3D textures have no mipmaps.
+    pub fn build_3d(self, ctx: &Context, slices: Vec<DynamicImage>) -> Texture3D {
+        assert!(!slices.is_empty(), "a volume needs at least one slice");
+        let (width, height) = slices[0].dimensions();
+        assert!(
+            slices
+                .iter()
+                .all(|slice| slice.dimensions() == (width, height)),
+            "volume slices differ in size"
+        );
+        let texture = Texture3D::create(ctx, self.format, width, height, slices.len() as u32);
+        let data = slices
+            .iter()
+            .flat_map(|slice| {
+                if self.format.is_integer() {
+                    TextureFormat::encode_integer(slice)
+                } else {
+                    self.format.encode(&slice.to_rgba32f())
+                }
+            })
+            .collect::<Vec<_>>();
+        write_texture_level(ctx, &texture.texture, self.format, 0, &data);
+        texture
+    }
+
+    /// Uses tightly packed voxels in the builder's format, slice by slice.
+    pub fn build_3d_from_pixels(
+        self,
+        ctx: &Context,
+        width: u32,
+        height: u32,
+        depth: u32,
+        data: &[u8],
+    ) -> Texture3D {
+        assert_eq!(
+            data.len(),
+            (width * height * depth * self.format.bytes_per_pixel()) as usize,
+            "voxel data does not match a {width}x{height}x{depth} {:?} volume",
+            self.format
+        );
+        let texture = Texture3D::create(ctx, self.format, width, height, depth);
+        write_texture_level(ctx, &texture.texture, self.format, 0, data);
+        texture
+    }
+
+    /// Uploads the first level of every layer and fills the remaining levels.
+    fn write_layers(&self, ctx: &Context, texture: &wgpu::Texture, images: Vec<DynamicImage>) {
+        if self.format.is_integer() {
+            let data = images
+                .iter()
+                .flat_map(TextureFormat::encode_integer)
+                .collect::<Vec<_>>();
+            write_texture_level(ctx, texture, self.format, 0, &data);
+        } else {
+            let layers = images
+                .into_iter()
+                .map(DynamicImage::into_rgba32f)
+                .collect::<Vec<_>>();
+            let data = layers
+                .iter()
+                .flat_map(|layer| self.format.encode(layer))
+                .collect::<Vec<_>>();
+            write_texture_level(ctx, texture, self.format, 0, &data);
+            fill_mip_levels(ctx, texture, self.format, || layers);
+        }
+    }
+
+    /// Mip level count and the extra usage needed to generate the levels on the GPU.
+    fn levels(&self, ctx: &Context, width: u32, height: u32) -> (u32, wgpu::TextureUsages) {
+        let mip_level_count = if self.mipmaps && !self.format.is_integer() {
+            mipmap::mip_level_count(width, height)
+        } else {
+            1
+        };
+        let generate =
+            mip_level_count > 1 && MipmapGenerator::supports(ctx.device(), self.format.into());
+        let usage = if generate {
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        } else {
+            wgpu::TextureUsages::empty()
+        };
+        (mip_level_count, usage)
+    }
+
+    /// Uses pre-built mip levels, each an image file half the size of the previous one.
+    pub fn build_from_mip_levels(self, ctx: &Context, levels: &[&[u8]]) -> Texture2D {
+        assert!(!levels.is_empty(), "a texture needs at least one mip level");
+        let images = levels
+            .iter()
+            .map(|data| image::load_from_memory(data).unwrap())
+            .collect::<Vec<_>>();
+        let (width, height) = images[0].dimensions();
+        let texture = Texture2D::create(
+            ctx,
+            self.format,
+            width,
+            height,
+            images.len() as u32,
+            wgpu::TextureUsages::empty(),
+        );
+        for (level, image) in images.into_iter().enumerate() {
+            let expected = texture
+                .texture
+                .size()
+                .mip_level_size(level as u32, wgpu::TextureDimension::D2);
+            assert_eq!(
+                image.dimensions(),
+                (expected.width, expected.height),
+                "mip level {level} has the wrong size"
+            );
+            let data = if self.format.is_integer() {
+                TextureFormat::encode_integer(&image)
+            } else {
+                self.format.encode(&image.into_rgba32f())
+            };
+            texture.write_level(ctx, level as u32, &data);
+        }
+        texture
+    }
+}
+
+pub struct Texture2D {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    format: TextureFormat,
+}
 
 impl Texture2D {
-    fn new_internal(device: &wgpu::Device, queue: &wgpu::Queue, image: RgbaImage) -> Self {
-        let dimentions = image.dimensions();
+    pub(crate) fn create(
+        ctx: &Context,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+        mip_level_count: u32,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
         let size = wgpu::Extent3d {
-            width: dimentions.0,
-            height: dimentions.1,
+            width,
+            height,
             depth_or_array_layers: 1,
         };
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: None,
+        let texture = create_texture(
+            ctx,
+            format,
             size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-        queue.write_texture(
+            wgpu::TextureDimension::D2,
+            mip_level_count,
+            usage,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            texture,
+            view,
+            format,
+        }
+    }
+
+    pub(crate) fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    fn write_level(&self, ctx: &Context, level: u32, data: &[u8]) {
+        write_texture_level(ctx, &self.texture, self.format, level, data);
+    }
+
+    fn fill_mip_levels(&self, ctx: &Context, level: impl FnOnce() -> Rgba32FImage) {
+        fill_mip_levels(ctx, &self.texture, self.format, || vec![level()]);
+    }
+
+    /// Decodes an image file into an sRGB texture and generates its full mip chain.
+    pub fn new(ctx: &Context, data: &[u8]) -> Self {
+        Texture2DBuilder::new().build(ctx, data)
+    }
+
+    pub fn new_without_mipmaps(ctx: &Context, data: &[u8]) -> Self {
+        Texture2DBuilder::new().with_mipmaps(false).build(ctx, data)
+    }
+
+    /// Decodes a `.hdr` or `.exr` file, or any other image, into a half float texture.
+    pub fn new_hdr(ctx: &Context, data: &[u8]) -> Self {
+        Texture2DBuilder::new()
+            .with_format(TextureFormat::Rgba16Float)
+            .build(ctx, data)
+    }
+
+    pub fn from_mip_levels(ctx: &Context, levels: &[&[u8]]) -> Self {
+        Texture2DBuilder::new().build_from_mip_levels(ctx, levels)
+    }
+
+    /// Loads the first layer of a KTX2 file holding BC, ETC2 or ASTC blocks with its mip levels.
+    pub fn from_ktx2(ctx: &Context, data: &[u8]) -> Result<Self, CompressedTextureError> {
+        Self::from_compressed(ctx, CompressedImage::from_ktx2(data)?)
+    }
+
+    /// Loads the first layer of a DDS file holding BC blocks with its mip levels.
+    pub fn from_dds(ctx: &Context, data: &[u8]) -> Result<Self, CompressedTextureError> {
+        Self::from_compressed(ctx, CompressedImage::from_dds(data)?)
+    }
+
+    /// Uploads the blocks when the adapter supports the format and decompresses them otherwise.
+    fn from_compressed(
+        ctx: &Context,
+        image: CompressedImage,
+    ) -> Result<Self, CompressedTextureError> {
+        let level_count = image.levels.len() as u32;
+        if image.is_supported(ctx) {
+            let format = TextureFormat::Compressed(image.format);
+            let texture = Self::create(
+                ctx,
+                format,
+                image.width,
+                image.height,
+                level_count,
+                wgpu::TextureUsages::empty(),
+            );
+            for (level, data) in image.levels.iter().enumerate() {
+                texture.write_level(ctx, level as u32, data);
+            }
+            return Ok(texture);
+        }
+        log::warn!("{:?} is not supported, decompressing it", image.format);
+        let (format, levels) = image.decompress()?;
+        let texture = Self::create(
+            ctx,
+            format,
+            image.width,
+            image.height,
+            level_count,
+            wgpu::TextureUsages::empty(),
+        );
+        for (level, data) in levels.iter().enumerate() {
+            texture.write_level(ctx, level as u32, data);
+        }
+        Ok(texture)
+    }
+
+    /// Creates an sRGB texture without mipmaps from tightly packed RGBA8 pixels.
+    pub fn from_rgba(ctx: &Context, width: u32, height: u32, data: &[u8]) -> Self {
+        Texture2DBuilder::new()
+            .with_mipmaps(false)
+            .build_from_pixels(ctx, width, height, data)
+    }
+
+    /// Creates a zeroed sRGB texture without mipmaps.
+    pub fn empty(ctx: &Context, width: u32, height: u32) -> Self {
+        Texture2DBuilder::new()
+            .with_mipmaps(false)
+            .build_empty(ctx, width, height)
+    }
+
+    /// Replaces a rectangle of the first level with tightly packed pixels in the texture's format.
+    /// Other levels are left as they are until [`Texture2D::generate_mipmaps`] is called.
+    /// Compressed formats take whole blocks, the region aligned to them unless it ends at the
+    /// texture's edge.
+    pub fn write_region(
+        &self,
+        ctx: &Context,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) {
+        assert!(
+            x + width <= self.width() && y + height <= self.height(),
+            "region is outside of the texture"
+        );
+        let format = wgpu::TextureFormat::from(self.format);
+        let (block_width, block_height) = format.block_dimensions();
+        assert!(
+            x.is_multiple_of(block_width)
+                && y.is_multiple_of(block_height)
+                && (width.is_multiple_of(block_width) || x + width == self.width())
+                && (height.is_multiple_of(block_height) || y + height == self.height()),
+            "region is not aligned to the {block_width}x{block_height} blocks of {format:?}"
+        );
+        let (columns, rows) = (width.div_ceil(block_width), height.div_ceil(block_height));
+        assert_eq!(
+            data.len(),
+            (columns * rows * self.format.bytes_per_pixel()) as usize,
+            "pixel data does not match the region size"
+        );
+        ctx.queue().write_texture(
             wgpu::ImageCopyTexture {
-                texture: &texture,
+                texture: &self.texture,
                 mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
+                origin: wgpu::Origin3d { x, y, z: 0 },
                 aspect: wgpu::TextureAspect::All,
             },
-            &image,
+            data,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(4 * dimentions.0),
-                rows_per_image: Some(dimentions.1),
+                bytes_per_row: Some(self.format.bytes_per_pixel() * columns),
+                rows_per_image: Some(rows),
+            },
+            wgpu::Extent3d {
+                width: columns * block_width,
+                height: rows * block_height,
+                depth_or_array_layers: 1,
             },
-            size,
         );
-        Self(texture.create_view(&wgpu::TextureViewDescriptor::default()))
     }
 
-    pub fn new(ctx: &Context, data: &[u8]) -> Self {
-        let image = image::load_from_memory(data).unwrap().to_rgba8();
-        Self::new_internal(ctx.device(), ctx.queue(), image)
+    /// Regenerates levels after the first from the first one. Only textures whose format can be
+    /// rendered to support this, others keep the levels they were created with.
+    pub fn generate_mipmaps(&self, ctx: &Context) {
+        if self
+            .texture
+            .usage()
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+        {
+            ctx.mipmap_generator()
+                .generate(ctx.device(), ctx.queue(), &self.texture);
+        } else if self.mip_level_count() > 1 {
+            log::warn!(
+                "{:?} mipmaps can only be generated on creation",
+                self.format
+            );
+        }
+    }
+
+    /// Reads the first level back with the values as stored, compressed formats decoded.
+    fn read(&self, ctx: &Context) -> Result<Rgba32FImage, ReadbackError> {
+        let data = read_texture_level(ctx, &self.texture, self.format, 0)?;
+        let (format, data) = match self.format {
+            TextureFormat::Compressed(format) => {
+                let image = CompressedImage {
+                    format,
+                    width: self.width(),
+                    height: self.height(),
+                    levels: vec![data],
+                };
+                let (format, mut levels) = image
+                    .decompress()
+                    .map_err(|_| ReadbackError::UnsupportedFormat(format))?;
+                (format, levels.swap_remove(0))
+            }
+            format => (format, data),
+        };
+        format
+            .decode(self.width(), self.height(), &data)
+            .ok_or(ReadbackError::UnsupportedFormat(format.into()))
+    }
+
+    fn is_float(&self) -> bool {
+        matches!(
+            self.format,
+            TextureFormat::R16Float
+                | TextureFormat::Rgba16Float
+                | TextureFormat::R32Float
+                | TextureFormat::Rgba32Float
+        )
+    }
+
+    /// Reads the first level back in linear color, decoding sRGB formats. Float values are kept
+    /// as they are.
+    pub fn to_rgba32f_image(&self, ctx: &Context) -> Result<Rgba32FImage, ReadbackError> {
+        let mut image = self.read(ctx)?;
+        if wgpu::TextureFormat::from(self.format).is_srgb() {
+            for pixel in image.pixels_mut() {
+                for value in &mut pixel.0[..3] {
+                    *value = mipmap::srgb_to_linear(*value);
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    /// Reads the first level back as 8 bit sRGB. Float formats are treated as linear HDR color,
+    /// Reinhard tonemapped and encoded, other formats keep their stored values.
+    pub fn to_rgba_image(&self, ctx: &Context) -> Result<RgbaImage, ReadbackError> {
+        let image = self.read(ctx)?;
+        let float = self.is_float();
+        Ok(RgbaImage::from_fn(self.width(), self.height(), |x, y| {
+            let pixel = image.get_pixel(x, y).0;
+            image::Rgba(std::array::from_fn(|channel| {
+                let value = if float && channel < 3 {
+                    let value = pixel[channel].max(0.0);
+                    mipmap::linear_to_srgb(value / (1.0 + value))
+                } else {
+                    pixel[channel]
+                };
+                (value * 255.0).round().clamp(0.0, 255.0) as u8
+            }))
+        }))
+    }
+
+    /// Saves the first level as a PNG, converted like [`Texture2D::to_rgba_image`].
+    pub fn save_png(&self, ctx: &Context, path: impl AsRef<Path>) -> Result<(), ReadbackError> {
+        Ok(self
+            .to_rgba_image(ctx)?
+            .save_with_format(path, ImageFormat::Png)?)
+    }
+
+    /// Saves the first level as an OpenEXR file with the linear values of
+    /// [`Texture2D::to_rgba32f_image`], without tonemapping.
+    pub fn save_exr(&self, ctx: &Context, path: impl AsRef<Path>) -> Result<(), ReadbackError> {
+        Ok(self
+            .to_rgba32f_image(ctx)?
+            .save_with_format(path, ImageFormat::OpenExr)?)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.height()
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.texture.mip_level_count()
     }
 }
 
 impl BindGroupEntry for Texture2D {
     fn visibility(&self) -> wgpu::ShaderStages {
-        wgpu::ShaderStages::FRAGMENT
+        wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE
     }
 
     fn binding_type(&self) -> wgpu::BindingType {
         wgpu::BindingType::Texture {
-            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            sample_type: self.format.sample_type(),
             view_dimension: wgpu::TextureViewDimension::D2,
             multisampled: false,
         }
     }
 
-    fn resource(&self) -> wgpu::BindingResource {
-        wgpu::BindingResource::TextureView(&self.0)
+    fn resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::TextureView(&self.view)
+    }
+}
+
+/// Layers of equal size sampled as `texture_2d_array`, e.g. the tiles of an atlas.
+pub struct Texture2DArray {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    format: TextureFormat,
+}
+
+impl Texture2DArray {
+    fn create(
+        ctx: &Context,
+        format: TextureFormat,
+        width: u32,
+        height: u32,
+        layers: u32,
+        mip_level_count: u32,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        };
+        let texture = create_texture(
+            ctx,
+            format,
+            size,
+            wgpu::TextureDimension::D2,
+            mip_level_count,
+            usage,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        Self {
+            texture,
+            view,
+            format,
+        }
+    }
+
+    /// Decodes one image file per layer into an sRGB array with mipmaps.
+    pub fn new(ctx: &Context, layers: &[&[u8]]) -> Self {
+        let images = layers
+            .iter()
+            .map(|data| image::load_from_memory(data).unwrap())
+            .collect();
+        Texture2DBuilder::new().build_array(ctx, images)
+    }
+
+    /// Decodes an atlas image file and makes each `tile_width` x `tile_height` tile a layer.
+    pub fn from_atlas(ctx: &Context, data: &[u8], tile_width: u32, tile_height: u32) -> Self {
+        Texture2DBuilder::new().build_array_from_atlas(
+            ctx,
+            image::load_from_memory(data).unwrap(),
+            tile_width,
+            tile_height,
+        )
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.height()
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.texture.depth_or_array_layers()
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.texture.mip_level_count()
+    }
+}
+
+impl BindGroupEntry for Texture2DArray {
+    fn visibility(&self) -> wgpu::ShaderStages {
+        wgpu::ShaderStages::FRAGMENT
+    }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        wgpu::BindingType::Texture {
+            sample_type: self.format.sample_type(),
+            view_dimension: wgpu::TextureViewDimension::D2Array,
+            multisampled: false,
+        }
+    }
+
+    fn resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::TextureView(&self.view)
+    }
+}
+
+pub struct TextureCube {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    format: TextureFormat,
+}
+
+impl TextureCube {
+    fn create(
+        ctx: &Context,
+        format: TextureFormat,
+        size: u32,
+        mip_level_count: u32,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        };
+        let texture = create_texture(
+            ctx,
+            format,
+            size,
+            wgpu::TextureDimension::D2,
+            mip_level_count,
+            usage,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        Self {
+            texture,
+            view,
+            format,
+        }
+    }
+
+    /// Decodes six image files in `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z` order into an sRGB cube.
+    pub fn new(ctx: &Context, faces: [&[u8]; 6]) -> Self {
+        let faces = faces
+            .iter()
+            .map(|data| image::load_from_memory(data).unwrap())
+            .collect();
+        Texture2DBuilder::new().build_cube(ctx, faces)
+    }
+
+    pub fn from_cross(ctx: &Context, data: &[u8]) -> Self {
+        Texture2DBuilder::new().build_cube_from_cross(ctx, image::load_from_memory(data).unwrap())
+    }
+
+    /// Decodes a panorama, usually `.hdr`, into a half float cube with faces of `size` pixels.
+    pub fn from_equirectangular(ctx: &Context, data: &[u8], size: u32) -> Self {
+        Texture2DBuilder::new()
+            .with_format(TextureFormat::Rgba16Float)
+            .build_cube_from_equirectangular(ctx, image::load_from_memory(data).unwrap(), size)
+    }
+
+    pub fn size(&self) -> u32 {
+        self.texture.width()
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.texture.mip_level_count()
+    }
+}
+
+impl BindGroupEntry for TextureCube {
+    fn visibility(&self) -> wgpu::ShaderStages {
+        wgpu::ShaderStages::FRAGMENT
+    }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        wgpu::BindingType::Texture {
+            sample_type: self.format.sample_type(),
+            view_dimension: wgpu::TextureViewDimension::Cube,
+            multisampled: false,
+        }
+    }
+
+    fn resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::TextureView(&self.view)
+    }
+}
+
+pub struct Texture3D {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    format: TextureFormat,
+}
+
+impl Texture3D {
+    fn create(ctx: &Context, format: TextureFormat, width: u32, height: u32, depth: u32) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: depth,
+        };
+        let texture = create_texture(
+            ctx,
+            format,
+            size,
+            wgpu::TextureDimension::D3,
+            1,
+            wgpu::TextureUsages::empty(),
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            texture,
+            view,
+            format,
+        }
+    }
+
+    /// Decodes one image file per slice into a volume in `format`.
+    pub fn new(ctx: &Context, format: TextureFormat, slices: &[&[u8]]) -> Self {
+        let slices = slices
+            .iter()
+            .map(|data| image::load_from_memory(data).unwrap())
+            .collect();
+        Texture2DBuilder::new()
+            .with_format(format)
+            .build_3d(ctx, slices)
+    }
+
+    /// Creates a single channel density volume from one byte per voxel.
+    pub fn from_density(ctx: &Context, width: u32, height: u32, depth: u32, data: &[u8]) -> Self {
+        Texture2DBuilder::new()
+            .with_format(TextureFormat::R8Unorm)
+            .build_3d_from_pixels(ctx, width, height, depth, data)
+    }
+
+    pub fn size(&self) -> UVec3 {
+        let size = self.texture.size();
+        uvec3(size.width, size.height, size.depth_or_array_layers)
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+}
+
+impl BindGroupEntry for Texture3D {
+    fn visibility(&self) -> wgpu::ShaderStages {
+        wgpu::ShaderStages::FRAGMENT
+    }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        wgpu::BindingType::Texture {
+            sample_type: self.format.sample_type(),
+            view_dimension: wgpu::TextureViewDimension::D3,
+            multisampled: false,
+        }
+    }
+
+    fn resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::TextureView(&self.view)
+    }
+}
+
+/// Texture compute shaders load from and store to, which can also be sampled once written.
+/// Reading needs `Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES`; `R32Float` and `R32Uint`
+/// support it everywhere that feature is available.
+pub struct StorageTexture {
+    texture: Texture2D,
+    read_only: StorageView,
+    write_only: StorageView,
+    read_write: StorageView,
+}
+
+impl StorageTexture {
+    pub fn new(ctx: &Context, format: TextureFormat, width: u32, height: u32) -> Self {
+        let allowed_usages = wgpu::TextureFormat::from(format)
+            .guaranteed_format_features(ctx.features())
+            .allowed_usages;
+        assert!(
+            allowed_usages.contains(wgpu::TextureUsages::STORAGE_BINDING),
+            "{format:?} can't be used as a storage texture"
+        );
+        let texture = Texture2D::create(
+            ctx,
+            format,
+            width,
+            height,
+            1,
+            wgpu::TextureUsages::STORAGE_BINDING,
+        );
+        let view = |access| StorageView {
+            view: texture.texture.create_view(&Default::default()),
+            format,
+            access,
+        };
+        Self {
+            read_only: view(StorageAccess::ReadOnly),
+            write_only: view(StorageAccess::WriteOnly),
+            read_write: view(StorageAccess::ReadWrite),
+            texture,
+        }
+    }
+
+    pub fn read_only(&self) -> &StorageView {
+        &self.read_only
+    }
+
+    pub fn write_only(&self) -> &StorageView {
+        &self.write_only
+    }
+
+    pub fn read_write(&self) -> &StorageView {
+        &self.read_write
+    }
+
+    /// The same texture bound for sampling.
+    pub fn sampled(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.height()
     }
+
+    pub fn format(&self) -> TextureFormat {
+        self.texture.format()
+    }
+}
+
+/// Binding of a [`StorageTexture`] with one access mode.
+pub struct StorageView {
+    view: wgpu::TextureView,
+    format: TextureFormat,
+    access: StorageAccess,
 }
 
-pub struct Sampler(wgpu::Sampler);
+impl BindGroupEntry for StorageView {
+    fn visibility(&self) -> wgpu::ShaderStages {
+        wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE
+    }
+
+    fn binding_type(&self) -> wgpu::BindingType {
+        wgpu::BindingType::StorageTexture {
+            access: self.access,
+            format: self.format.into(),
+            view_dimension: wgpu::TextureViewDimension::D2,
+        }
+    }
+
+    fn resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::TextureView(&self.view)
+    }
+}
+
+pub struct SamplerBuilder {
+    address_modes: [AddressMode; 3],
+    border_color: Option<SamplerBorderColor>,
+    mag_filter: FilterMode,
+    min_filter: FilterMode,
+    mipmap_filter: FilterMode,
+    lod_min_clamp: f32,
+    lod_max_clamp: f32,
+    anisotropy: u16,
+    compare: Option<CompareFunction>,
+}
+
+impl Default for SamplerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SamplerBuilder {
+    pub fn new() -> Self {
+        Self {
+            address_modes: [AddressMode::ClampToEdge; 3],
+            border_color: None,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Linear,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 32.0,
+            anisotropy: 1,
+            compare: None,
+        }
+    }
+
+    pub fn with_address_mode(self, mode: AddressMode) -> Self {
+        self.with_address_modes(mode, mode, mode)
+    }
+
+    pub fn with_address_modes(mut self, u: AddressMode, v: AddressMode, w: AddressMode) -> Self {
+        self.address_modes = [u, v, w];
+        self
+    }
+
+    /// Uses [`AddressMode::ClampToBorder`] on every axis. Falls back to clamping to the edge
+    /// when the adapter has no `Features::ADDRESS_MODE_CLAMP_TO_BORDER`.
+    pub fn with_border(mut self, color: SamplerBorderColor) -> Self {
+        self.border_color = Some(color);
+        self.with_address_mode(AddressMode::ClampToBorder)
+    }
+
+    pub fn with_filter(self, filter: FilterMode) -> Self {
+        self.with_filters(filter, filter, filter)
+    }
+
+    pub fn with_filters(mut self, mag: FilterMode, min: FilterMode, mipmap: FilterMode) -> Self {
+        self.mag_filter = mag;
+        self.min_filter = min;
+        self.mipmap_filter = mipmap;
+        self
+    }
+
+    pub fn with_lod_clamp(mut self, min: f32, max: f32) -> Self {
+        self.lod_min_clamp = min;
+        self.lod_max_clamp = max;
+        self
+    }
+
+    /// Clamped to 1..=16. Only applies when all filters are linear.
+    pub fn with_anisotropy(mut self, anisotropy: u16) -> Self {
+        self.anisotropy = anisotropy.clamp(1, 16);
+        self
+    }
+
+    /// Makes a comparison sampler for depth textures, e.g. shadow maps drawn into a
+    /// [`RenderTarget::depth_texture`](crate::RenderTarget::depth_texture).
+    pub fn with_compare(mut self, compare: CompareFunction) -> Self {
+        self.compare = Some(compare);
+        self
+    }
+
+    pub fn build(self, ctx: &Context) -> Sampler {
+        let mut address_modes = self.address_modes;
+        if address_modes.contains(&AddressMode::ClampToBorder)
+            && !ctx
+                .features()
+                .contains(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER)
+        {
+            log::warn!("clamp to border is not supported, clamping to edge instead");
+            for mode in &mut address_modes {
+                if *mode == AddressMode::ClampToBorder {
+                    *mode = AddressMode::ClampToEdge;
+                }
+            }
+        }
+        let linear = [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .iter()
+            .all(|&filter| filter == FilterMode::Linear);
+        let anisotropy_clamp = if linear {
+            self.anisotropy
+        } else {
+            if self.anisotropy > 1 {
+                log::warn!("anisotropic filtering requires linear filters, ignoring it");
+            }
+            1
+        };
+        let binding_type = if self.compare.is_some() {
+            wgpu::SamplerBindingType::Comparison
+        } else if [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .contains(&FilterMode::Linear)
+        {
+            wgpu::SamplerBindingType::Filtering
+        } else {
+            wgpu::SamplerBindingType::NonFiltering
+        };
+        let sampler = ctx.device().create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: address_modes[0],
+            address_mode_v: address_modes[1],
+            address_mode_w: address_modes[2],
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: self.lod_min_clamp,
+            lod_max_clamp: self.lod_max_clamp,
+            compare: self.compare,
+            anisotropy_clamp,
+            border_color: self.border_color,
+        });
+        Sampler {
+            sampler,
+            binding_type,
+        }
+    }
+}
+
+pub struct Sampler {
+    sampler: wgpu::Sampler,
+    binding_type: wgpu::SamplerBindingType,
+}
 
 impl Sampler {
+    /// Clamps to the edge with nearest texel and linear mip filtering.
     pub fn new(ctx: &Context) -> Self {
-        Self(ctx.device().create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        }))
+        SamplerBuilder::new().build(ctx)
+    }
+
+    pub fn linear(ctx: &Context) -> Self {
+        SamplerBuilder::new()
+            .with_filter(FilterMode::Linear)
+            .build(ctx)
+    }
+
+    pub fn repeat(ctx: &Context) -> Self {
+        SamplerBuilder::new()
+            .with_address_mode(AddressMode::Repeat)
+            .with_filter(FilterMode::Linear)
+            .build(ctx)
     }
 }
 
 impl BindGroupEntry for Sampler {
     fn visibility(&self) -> wgpu::ShaderStages {
-        wgpu::ShaderStages::FRAGMENT
+        wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE
     }
 
     fn binding_type(&self) -> wgpu::BindingType {
-        wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
+        wgpu::BindingType::Sampler(self.binding_type)
     }
 
-    fn resource(&self) -> wgpu::BindingResource {
-        wgpu::BindingResource::Sampler(&self.0)
+    fn resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Sampler(&self.sampler)
     }
 }
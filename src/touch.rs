@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use glam::*;
+use winit::event::TouchPhase;
+
+/// Distance in physical pixels a finger can move before a touch stops being a tap.
+const TAP_SLOP: f32 = 16.0;
+
+/// Longest a touch can last to count as a [`Gesture::Tap`].
+const TAP_TIME: Duration = Duration::from_millis(300);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Touch {
+    /// Identifies the finger for as long as it touches the screen.
+    pub id: u64,
+    pub phase: TouchPhase,
+    /// Position in physical pixels.
+    pub position: Vec2,
+    /// Pressure from 0 to 1, when the device reports it.
+    pub force: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    /// A single finger lifted quickly without moving. Its duration is counted in whole updates, so
+    /// a tap spanning a long frame may not count.
+    Tap(Vec2),
+    /// A single finger moved by `delta` to `position`.
+    Drag { position: Vec2, delta: Vec2 },
+    /// Two fingers moved apart by a factor of `scale` around `center`.
+    Pinch { center: Vec2, scale: f32 },
+    /// The center between two fingers moved by `delta`.
+    Pan { center: Vec2, delta: Vec2 },
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct GestureRecognizer {
+    /// First finger of the current gesture, where and when it went down.
+    start: Option<(u64, Vec2, Duration)>,
+    dragging: bool,
+    /// Set once a second finger joins, until all of them are lifted.
+    multi_touch: bool,
+    /// Center and distance of the first two fingers at the last move.
+    pair: Option<(Vec2, f32)>,
+}
+
+impl GestureRecognizer {
+    /// Recognizes gestures from a touch, given the previous state of its finger and every finger
+    /// on the screen after it.
+    pub(crate) fn touch(
+        &mut self,
+        touch: Touch,
+        previous: Option<Touch>,
+        touches: &[Touch],
+        time: Duration,
+    ) -> Vec<Gesture> {
+        let mut gestures = Vec::new();
+        match touch.phase {
+            TouchPhase::Started => {
+                if touches.len() == 1 {
+                    *self = Self {
+                        start: Some((touch.id, touch.position, time)),
+                        ..Self::default()
+                    };
+                } else {
+                    self.multi_touch = true;
+                    self.pair = None;
+                }
+            }
+            TouchPhase::Moved if self.multi_touch => {
+                if let [first, second, ..] = touches {
+                    let center = (first.position + second.position) / 2.0;
+                    let distance = first.position.distance(second.position);
+                    if let Some((last_center, last_distance)) = self.pair {
+                        if distance != last_distance && last_distance > 0.0 {
+                            gestures.push(Gesture::Pinch {
+                                center,
+                                scale: distance / last_distance,
+                            });
+                        }
+                        if center != last_center {
+                            gestures.push(Gesture::Pan {
+                                center,
+                                delta: center - last_center,
+                            });
+                        }
+                    }
+                    self.pair = Some((center, distance));
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some((_, start, _)) = self.start.filter(|&(id, ..)| id == touch.id) {
+                    self.dragging |= touch.position.distance(start) > TAP_SLOP;
+                    if self.dragging {
+                        let last = previous.map_or(start, |previous| previous.position);
+                        gestures.push(Gesture::Drag {
+                            position: touch.position,
+                            delta: touch.position - last,
+                        });
+                    }
+                }
+            }
+            TouchPhase::Ended => {
+                if let Some((_, _, start_time)) = self.start.filter(|&(id, ..)| id == touch.id) {
+                    if !self.multi_touch && !self.dragging && time - start_time <= TAP_TIME {
+                        gestures.push(Gesture::Tap(touch.position));
+                    }
+                }
+                self.pair = None;
+            }
+            TouchPhase::Cancelled => {
+                self.start = None;
+                self.pair = None;
+            }
+        }
+        gestures
+    }
+}
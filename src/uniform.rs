@@ -1,12 +1,16 @@
 use crate::data;
 
+/// # Safety
+///
+/// Implemented by [`impl_uniform!`](crate::impl_uniform), which lays the type out with `#[repr(C)]`.
 pub unsafe trait Uniform : data::Pod + data::Zeroable { }
 
 pub fn check_data_for_pod<T: data::Pod + data::Zeroable>(_: &T) {}
 
 #[macro_export]
 macro_rules! impl_uniform {
-    {$visibility:vis struct $name:ident { $($field:ident: $field_type:ty),* $(,)?} } => {
+    {$(#[$attribute:meta])* $visibility:vis struct $name:ident { $($field:ident: $field_type:ty),* $(,)?} } => {
+        $(#[$attribute])*
         #[repr(C)]
         #[derive(Clone, Copy)]
         $visibility struct $name {
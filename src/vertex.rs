@@ -6,7 +6,7 @@ pub struct Layout {
 }
 
 impl Layout {
-    pub(crate) fn as_vertex_buffer_layout(&self) -> wgpu::VertexBufferLayout {
+    pub(crate) fn as_vertex_buffer_layout(&self) -> wgpu::VertexBufferLayout<'_> {
         wgpu::VertexBufferLayout {
             array_stride: self.stride,
             step_mode: wgpu::VertexStepMode::Vertex,
@@ -56,6 +56,9 @@ impl LayoutBuilder {
     }
 }
 
+/// # Safety
+///
+/// Implemented by [`impl_vertex!`](crate::impl_vertex), which lays the type out with `#[repr(C)]`.
 pub unsafe trait Vertex: data::Pod + data::Zeroable {
     fn layout() -> Layout;
 }
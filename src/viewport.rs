@@ -0,0 +1,395 @@
+use std::cell::{Cell, OnceCell};
+
+use elsa::FrozenMap;
+use glam::*;
+
+use crate::{
+    frame::Target, uniform, BindGroup, BindGroupBuilder, BindGroupBuilderWith, Context, Frame, Pass,
+};
+use crate::{
+    impl_uniform, pipeline::FallbackPushConstants, PipelineBuilder, RenderTarget, UniformBuffer,
+};
+
+const CLEAR_VERTEX_SHADER: &str = "
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 1.0, 1.0);
+}
+";
+
+/// Scaled by the blend constant.
+const CLEAR_BLEND_SHADER: &str = "
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
+";
+
+/// `SCALAR` replaced by the value type of the target.
+const CLEAR_VALUE_SHADER: &str = "
+@group(0) @binding(0) var<uniform> value: vec4<SCALAR>;
+
+@fragment
+fn fs_main() -> @location(0) vec4<SCALAR> {
+    return value;
+}
+";
+
+/// Region of a render target in normalized coordinates, `(0, 0)` being the top left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub clear: Option<Vec4>,
+}
+
+impl Viewport {
+    pub fn new(position: Vec2, size: Vec2) -> Self {
+        Self {
+            position,
+            size,
+            clear: None,
+        }
+    }
+
+    pub fn full() -> Self {
+        Self::new(Vec2::ZERO, Vec2::ONE)
+    }
+
+    pub fn with_clear(mut self, r: f32, g: f32, b: f32, a: f32) -> Self {
+        self.clear = Some(vec4(r, g, b, a));
+        self
+    }
+
+    /// Splits the target into `columns` x `rows` equal cells, row by row.
+    pub fn grid(columns: u32, rows: u32) -> Vec<Self> {
+        let size = vec2(1.0 / columns as f32, 1.0 / rows as f32);
+        (0..rows)
+            .flat_map(|row| {
+                (0..columns)
+                    .map(move |column| Self::new(vec2(column as f32, row as f32) * size, size))
+            })
+            .collect()
+    }
+
+    /// Side by side, left to right.
+    pub fn split_horizontal(count: u32) -> Vec<Self> {
+        Self::grid(count, 1)
+    }
+
+    /// Stacked, top to bottom.
+    pub fn split_vertical(count: u32) -> Vec<Self> {
+        Self::grid(1, count)
+    }
+
+    /// Pixel position and size of the region inside a target of `target` pixels.
+    pub fn rect(&self, target: Vec2) -> (Vec2, Vec2) {
+        let min = (self.position * target).round().clamp(Vec2::ZERO, target);
+        let max = ((self.position + self.size) * target)
+            .round()
+            .clamp(Vec2::ZERO, target);
+        (min, (max - min).max(Vec2::ONE))
+    }
+
+    pub fn aspect(&self, target: Vec2) -> f32 {
+        let (_, size) = self.rect(target);
+        size.x / size.y
+    }
+}
+
+/// Maps view space to clip space for any aspect ratio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Vertical field of view in radians.
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    /// Visible height in world units, the width following the aspect ratio.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect: f32) -> Mat4 {
+        match *self {
+            Projection::Perspective { fov_y, near, far } => {
+                Mat4::perspective_rh(fov_y, aspect, near, far)
+            }
+            Projection::Orthographic { height, near, far } => {
+                let (x, y) = (height * aspect / 2.0, height / 2.0);
+                Mat4::orthographic_rh(-x, x, -y, y, near, far)
+            }
+        }
+    }
+}
+
+impl_uniform! {
+    /// Uniform of views created by [`View::camera`], in WGSL
+    /// `struct View { view_proj: mat4x4<f32>, view: mat4x4<f32>, projection: mat4x4<f32> }`.
+    pub struct ViewUniform {
+        view_proj: [[f32; 4]; 4],
+        view: [[f32; 4]; 4],
+        projection: [[f32; 4]; 4],
+    }
+}
+
+impl ViewUniform {
+    fn new(view: Mat4, projection: Mat4) -> Self {
+        Self {
+            view_proj: (projection * view).to_cols_array_2d(),
+            view: view.to_cols_array_2d(),
+            projection: projection.to_cols_array_2d(),
+        }
+    }
+}
+
+/// Viewport with its own uniform, usually holding a camera.
+pub struct View {
+    pub viewport: Viewport,
+    buffer: UniformBuffer,
+    bind_group: BindGroup,
+    /// Projection and view matrix of views created by [`View::camera`].
+    camera: Option<(Projection, Cell<Mat4>)>,
+}
+
+impl View {
+    pub fn new<T: uniform::Uniform>(
+        ctx: &Context,
+        viewport: Viewport,
+        data: &T,
+        pipeline_builder: &mut PipelineBuilder,
+    ) -> Self {
+        let buffer = UniformBuffer::new(ctx, data);
+        let bind_group = BindGroupBuilder::new()
+            .with(&buffer)
+            .build(ctx, pipeline_builder);
+        Self {
+            viewport,
+            buffer,
+            bind_group,
+            camera: None,
+        }
+    }
+
+    /// Creates a view sharing the bind group layout registered by an earlier [`View::new`].
+    pub fn new_shared<T: uniform::Uniform>(ctx: &Context, viewport: Viewport, data: &T) -> Self {
+        let buffer = UniformBuffer::new(ctx, data);
+        let bind_group = BindGroupBuilder::new().with(&buffer).build_shared(ctx);
+        Self {
+            viewport,
+            buffer,
+            bind_group,
+            camera: None,
+        }
+    }
+
+    /// Creates a view holding a [`ViewUniform`], its projection matching the aspect ratio of the
+    /// region every time a pass begins, so resizes need no extra work.
+    pub fn camera(
+        ctx: &Context,
+        viewport: Viewport,
+        projection: Projection,
+        pipeline_builder: &mut PipelineBuilder,
+    ) -> Self {
+        let mut view = Self::new(
+            ctx,
+            viewport,
+            &ViewUniform::new(Mat4::IDENTITY, Mat4::IDENTITY),
+            pipeline_builder,
+        );
+        view.camera = Some((projection, Cell::new(Mat4::IDENTITY)));
+        view
+    }
+
+    /// Like [`View::camera`], sharing the bind group layout of an earlier view.
+    pub fn camera_shared(ctx: &Context, viewport: Viewport, projection: Projection) -> Self {
+        let mut view = Self::new_shared(
+            ctx,
+            viewport,
+            &ViewUniform::new(Mat4::IDENTITY, Mat4::IDENTITY),
+        );
+        view.camera = Some((projection, Cell::new(Mat4::IDENTITY)));
+        view
+    }
+
+    /// Sets the view matrix of a view created by [`View::camera`], written with the next pass.
+    pub fn set_camera(&self, view: Mat4) {
+        match &self.camera {
+            Some((_, camera)) => camera.set(view),
+            None => log::warn!("set_camera called on a view without a projection"),
+        }
+    }
+
+    pub fn aspect(&self, target: Vec2) -> f32 {
+        self.viewport.aspect(target)
+    }
+
+    /// Projection of a view created by [`View::camera`] inside a target of `target` pixels.
+    pub fn projection(&self, target: Vec2) -> Option<Mat4> {
+        self.camera
+            .as_ref()
+            .map(|(projection, _)| projection.matrix(self.aspect(target)))
+    }
+
+    pub fn write<T: uniform::Uniform>(&self, ctx: &Context, data: &T) {
+        self.buffer.write(ctx, 0, data);
+    }
+
+    pub fn attach<'a>(&'a self, pass: &mut Pass<'a>, index: u32) {
+        self.bind_group.attach(pass, index, &[]);
+    }
+
+    /// Writes the camera uniform for a target of `target` pixels. The buffer holds one value,
+    /// so a view drawn into targets of different shapes in one frame needs a view per target.
+    fn write_camera(&self, ctx: &Context, target: Vec2) {
+        if let Some((_, camera)) = &self.camera {
+            let projection = self.projection(target).unwrap();
+            self.write(ctx, &ViewUniform::new(camera.get(), projection));
+        }
+    }
+
+    /// Starts a pass restricted to the view with its uniform bound at `index`.
+    pub fn begin<'a>(&'a self, frame: &'a mut Frame, index: u32) -> Pass<'a> {
+        self.write_camera(frame.context(), frame.size());
+        let mut pass = frame.viewport(&self.viewport);
+        self.attach(&mut pass, index);
+        pass
+    }
+
+    /// Like [`View::begin`], drawing into `target` instead of the window.
+    pub fn begin_target<'a>(
+        &'a self,
+        frame: &'a mut Frame,
+        target: &'a RenderTarget,
+        index: u32,
+    ) -> Pass<'a> {
+        self.write_camera(frame.context(), target.size());
+        let mut pass = frame.target_viewport(target, &self.viewport);
+        self.attach(&mut pass, index);
+        pass
+    }
+}
+
+/// Clears a scissored region by drawing a fullscreen triangle with far depth and zero stencil.
+/// The color is the blend constant, or a uniform for targets that can't be blended. Pipelines
+/// are created for each target on first use.
+#[derive(Default)]
+pub(crate) struct ViewportClear {
+    pipelines: FrozenMap<Target, Box<wgpu::RenderPipeline>>,
+    /// Clear colors of the targets that can't be blended, written like push constants.
+    values: OnceCell<FallbackPushConstants>,
+}
+
+impl ViewportClear {
+    fn create_pipeline(
+        device: &wgpu::Device,
+        target: Target,
+        values: Option<&FallbackPushConstants>,
+    ) -> wgpu::RenderPipeline {
+        let fragment = match (values, target.format.sample_type(None, None)) {
+            (None, _) => CLEAR_BLEND_SHADER.to_owned(),
+            (_, Some(wgpu::TextureSampleType::Uint)) => CLEAR_VALUE_SHADER.replace("SCALAR", "u32"),
+            (_, Some(wgpu::TextureSampleType::Sint)) => CLEAR_VALUE_SHADER.replace("SCALAR", "i32"),
+            _ => CLEAR_VALUE_SHADER.replace("SCALAR", "f32"),
+        };
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(format!("{CLEAR_VERTEX_SHADER}{fragment}").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: values.map(FallbackPushConstants::layout).as_slice(),
+            push_constant_ranges: &[],
+        });
+        let blend = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let stencil = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Always,
+            fail_op: wgpu::StencilOperation::Replace,
+            depth_fail_op: wgpu::StencilOperation::Replace,
+            pass_op: wgpu::StencilOperation::Replace,
+        };
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: target.depth_format.map(|format| wgpu::DepthStencilState {
+                format: format.into(),
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState {
+                    front: stencil,
+                    back: stencil,
+                    read_mask: !0,
+                    write_mask: !0,
+                },
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: target.sample_count,
+                ..Default::default()
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target.format,
+                    blend: values.is_none().then_some(wgpu::BlendState {
+                        color: blend,
+                        alpha: blend,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        })
+    }
+
+    pub(crate) fn draw<'a>(&'a self, pass: &mut Pass<'a>, color: Vec4) {
+        let (ctx, target) = (pass.context, pass.target);
+        let values = (!target.blendable(ctx.device())).then(|| {
+            self.values.get_or_init(|| {
+                FallbackPushConstants::new(ctx, 0, wgpu::ShaderStages::FRAGMENT, 16)
+            })
+        });
+        let pipeline = match self.pipelines.get(&target) {
+            Some(pipeline) => pipeline,
+            None => {
+                let pipeline = Self::create_pipeline(ctx.device(), target, values);
+                self.pipelines.insert(target, Box::new(pipeline))
+            }
+        };
+        let Some(raw) = pass.render_pass("viewport clear") else {
+            return;
+        };
+        raw.set_pipeline(pipeline);
+        raw.set_blend_constant(wgpu::Color {
+            r: color.x as f64,
+            g: color.y as f64,
+            b: color.z as f64,
+            a: color.w as f64,
+        });
+        raw.set_stencil_reference(0);
+        if let Some(values) = values {
+            let value = match target.format.sample_type(None, None) {
+                Some(wgpu::TextureSampleType::Uint) => {
+                    bytemuck::cast(color.to_array().map(|value| value as u32))
+                }
+                Some(wgpu::TextureSampleType::Sint) => {
+                    bytemuck::cast(color.to_array().map(|value| value as i32))
+                }
+                _ => bytemuck::cast::<_, [u8; 16]>(color.to_array()),
+            };
+            values.set(pass, &value);
+        }
+        pass.encoder().draw(0..3, 0..1);
+    }
+}
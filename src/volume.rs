@@ -0,0 +1,158 @@
+use glam::*;
+
+use crate::{BindGroup, BindGroupBuilder, BindGroupBuilderWith, BlendState, CompareFunction};
+use crate::{Context, Face, FilterMode, Pass, Pipeline, PipelineBuilder, SamplerBuilder, Shader};
+use crate::{ShaderStages, Texture2D, Texture2DBuilder, Texture3D, TextureFormat};
+
+const SHADER: &str = "
+struct Volume {
+    model_view_proj: mat4x4<f32>,
+    camera: vec4<f32>,
+    steps: u32,
+    density: f32,
+};
+
+var<push_constant> volume: Volume;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) local: vec3<f32>,
+};
+
+const INDICES = array<u32, 36>(
+    1u, 3u, 7u, 1u, 7u, 5u, 0u, 4u, 6u, 0u, 6u, 2u,
+    0u, 1u, 5u, 0u, 5u, 4u, 2u, 6u, 7u, 2u, 7u, 3u,
+    0u, 2u, 3u, 0u, 3u, 1u, 4u, 5u, 7u, 4u, 7u, 6u,
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var indices = INDICES;
+    let corner = indices[index];
+    let local = vec3<f32>(f32(corner & 1u), f32((corner >> 1u) & 1u), f32((corner >> 2u) & 1u));
+    var out: VertexOutput;
+    out.position = volume.model_view_proj * vec4<f32>(local, 1.0);
+    out.local = local;
+    return out;
+}
+
+@group(0) @binding(0)
+var density: texture_3d<f32>;
+@group(0) @binding(1)
+var transfer: texture_2d<f32>;
+@group(0) @binding(2)
+var volume_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let origin = volume.camera.xyz;
+    let direction = normalize(in.local - origin);
+    let t0 = (vec3<f32>(0.0) - origin) / direction;
+    let t1 = (vec3<f32>(1.0) - origin) / direction;
+    let near = max(max(max(min(t0.x, t1.x), min(t0.y, t1.y)), min(t0.z, t1.z)), 0.0);
+    let far = min(min(max(t0.x, t1.x), max(t0.y, t1.y)), max(t0.z, t1.z));
+    let step = 1.7320508 / f32(volume.steps);
+    var color = vec4<f32>(0.0);
+    for (var t = near + step * 0.5; t < far && color.a < 0.99; t += step) {
+        let value = textureSampleLevel(density, volume_sampler, origin + direction * t, 0.0).r;
+        let sample = textureSampleLevel(transfer, volume_sampler, vec2<f32>(value, 0.5), 0.0);
+        let alpha = 1.0 - pow(1.0 - clamp(sample.a, 0.0, 0.9999), step * volume.density);
+        color += (1.0 - color.a) * vec4<f32>(sample.rgb * alpha, alpha);
+    }
+    return color;
+}
+";
+
+crate::impl_uniform! {
+    struct VolumeUniform {
+        model_view_proj: [[f32; 4]; 4],
+        camera: [f32; 4],
+        steps: u32,
+        density: f32,
+        padding: [f32; 2],
+    }
+}
+
+/// Ray-marches the red channel of a volume through a transfer function mapping it to color and
+/// opacity. The volume fills the unit cube of its model matrix and is blended over what was
+/// drawn before, so draw it after opaque geometry.
+pub struct VolumeRenderer {
+    pipeline: Pipeline,
+    bind_group: BindGroup,
+    steps: u32,
+    density: f32,
+}
+
+impl VolumeRenderer {
+    pub fn new(ctx: &Context, volume: &Texture3D, transfer_function: &Texture2D) -> Self {
+        let shader = Shader::new(ctx, SHADER.to_string());
+        let mut pipeline_builder = PipelineBuilder::new(&shader)
+            .with_depth(CompareFunction::LessEqual, false)
+            .with_cull_mode(Some(Face::Front))
+            .with_blend(BlendState::PREMULTIPLIED_ALPHA_BLENDING)
+            .with_push_constants::<VolumeUniform>(ShaderStages::VERTEX_FRAGMENT);
+        let sampler = SamplerBuilder::new()
+            .with_filter(FilterMode::Linear)
+            .build(ctx);
+        let bind_group = BindGroupBuilder::new()
+            .with(volume)
+            .with(transfer_function)
+            .with(&sampler)
+            .build(ctx, &mut pipeline_builder);
+        Self {
+            pipeline: pipeline_builder.build(ctx),
+            bind_group,
+            steps: 256,
+            density: 64.0,
+        }
+    }
+
+    /// Samples taken along the diagonal of the volume, 256 by default.
+    pub fn with_steps(mut self, steps: u32) -> Self {
+        self.steps = steps.max(1);
+        self
+    }
+
+    /// Opacity per unit of distance the transfer function alpha is scaled by, 64 by default.
+    pub fn with_density(mut self, density: f32) -> Self {
+        self.density = density;
+        self
+    }
+
+    /// Builds a 256 texel transfer function by interpolating `(value, color)` stops sorted by
+    /// value, alpha being the opacity.
+    pub fn transfer_function(ctx: &Context, stops: &[(f32, Vec4)]) -> Texture2D {
+        let image = image::Rgba32FImage::from_fn(256, 1, |x, _| {
+            let value = x as f32 / 255.0;
+            let color = match stops.iter().position(|&(stop, _)| stop > value) {
+                Some(0) => stops[0].1,
+                Some(index) => {
+                    let (start, from) = stops[index - 1];
+                    let (end, to) = stops[index];
+                    from.lerp(to, (value - start) / (end - start))
+                }
+                None => stops.last().map_or(Vec4::ZERO, |&(_, color)| color),
+            };
+            image::Rgba(color.to_array())
+        });
+        Texture2DBuilder::new()
+            .with_format(TextureFormat::Rgba16Float)
+            .with_mipmaps(false)
+            .build_from_image(ctx, image::DynamicImage::ImageRgba32F(image))
+    }
+
+    pub fn draw<'a>(&'a self, pass: &mut Pass<'a>, model: Mat4, view: Mat4, projection: Mat4) {
+        let camera = (model.inverse() * view.inverse()).w_axis;
+        let uniform = VolumeUniform {
+            model_view_proj: (projection * view * model).to_cols_array_2d(),
+            camera: (camera / camera.w).to_array(),
+            steps: self.steps,
+            density: self.density,
+            padding: [0.0; 2],
+        };
+        self.pipeline.attach(pass);
+        self.bind_group.attach(pass, 0, &[]);
+        pass.set_push_constants(&uniform);
+        pass.draw(0..36, 0..1);
+    }
+}
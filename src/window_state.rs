@@ -1,66 +1,217 @@
-use std::{sync::Arc, time::Instant};
+use std::{io, rc::Rc, sync::Arc, time::Instant};
 
-use glam::vec2;
+use glam::{ivec2, vec2, Vec2};
 use winit::{
-    event::WindowEvent,
+    dpi::PhysicalSize,
+    event::{DeviceEvent, Ime, MouseScrollDelta, WindowEvent},
     event_loop::ActiveEventLoop,
     keyboard::PhysicalKey,
     window::{Window, WindowAttributes},
 };
 
 use crate::{
+    context::Gpu,
     graphics_state::{self, GraphicsState},
-    State,
+    recording::{InputEvent, Recording, RecordingWriter},
+    Context, State, Touch,
 };
 
+/// Converts touchpad scrolling, reported in pixels, to lines like wheels report.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// Where the input passed to the user state comes from.
+pub(crate) enum InputMode {
+    Live,
+    /// Live input, written to the recording as it is delivered.
+    Record(RecordingWriter),
+    /// Recorded input, live input is ignored. The window is asked to take the recorded sizes
+    /// and closes at the end of the recording.
+    Replay(Recording),
+}
+
+/// A window with its own state, type erased so that an application can mix them.
+pub(crate) trait AppWindow {
+    fn event(&mut self, event_loop: &ActiveEventLoop, event: WindowEvent);
+    fn device_event(&mut self, event_loop: &ActiveEventLoop, event: DeviceEvent);
+    fn update(&mut self, event_loop: &ActiveEventLoop);
+    /// Flushes the recording, if any.
+    fn exit(&mut self);
+    fn context(&self) -> &Context;
+}
+
 pub struct WindowState<T> {
     window: Arc<Window>,
     graphics: GraphicsState,
     user_state: T,
     last_time: Instant,
+    input_mode: InputMode,
+    /// Size asked for in a replay, checked against the next resize.
+    requested_size: Option<PhysicalSize<u32>>,
 }
 
 impl<T: State> WindowState<T> {
-    pub fn new(event_loop: &ActiveEventLoop) -> Self {
+    pub(crate) fn new(event_loop: &ActiveEventLoop, input_mode: InputMode) -> Self {
+        Self::with_state(event_loop, input_mode, None, T::new)
+    }
+
+    /// Opens a window on the device of `gpu`, or on a new one.
+    pub(crate) fn with_state(
+        event_loop: &ActiveEventLoop,
+        input_mode: InputMode,
+        gpu: Option<Rc<Gpu>>,
+        new: impl FnOnce(&Context) -> T,
+    ) -> Self {
         let window = Arc::new(
             event_loop
                 .create_window(WindowAttributes::default())
                 .unwrap(),
         );
-        let graphics = pollster::block_on(graphics_state::GraphicsState::new(window.clone()));
-        let user_state = T::new(graphics.context());
-        Self {
+        let graphics = pollster::block_on(graphics_state::GraphicsState::new(window.clone(), gpu));
+        let user_state = new(graphics.context());
+        let mut window_state = Self {
             window,
             graphics,
             user_state,
             last_time: Instant::now(),
+            input_mode,
+            requested_size: None,
+        };
+        let ctx = window_state.graphics.context();
+        // The scale factor comes first so the replayed resize sees the recorded logical size.
+        let initial = [
+            InputEvent::ScaleFactor(ctx.scale_factor()),
+            InputEvent::Resized(ctx.size()),
+        ];
+        for event in &initial {
+            window_state.record(|writer| writer.push(event));
+        }
+        window_state
+    }
+
+    /// Stops recording with an error when writing fails, the app keeps running on live input.
+    fn record(&mut self, write: impl FnOnce(&mut RecordingWriter) -> io::Result<()>) {
+        if let InputMode::Record(writer) = &mut self.input_mode {
+            if let Err(error) = write(writer) {
+                log::error!("stopping the recording: {error}");
+                self.input_mode = InputMode::Live;
+            }
         }
     }
 
-    pub fn event(&mut self, event_loop: &ActiveEventLoop, event: WindowEvent) {
+    fn input(&mut self, event: InputEvent) {
+        if let InputMode::Replay(_) = self.input_mode {
+            return;
+        }
+        self.record(|writer| writer.push(&event));
+        event.apply(self.graphics.context_mut(), &mut self.user_state);
+    }
+
+    /// Asks the window for the size the recording resized the context to.
+    fn request_recorded_size(&mut self, size: Vec2) {
+        let size = PhysicalSize::new(size.x as u32, size.y as u32);
+        match self.window.request_inner_size(size) {
+            Some(size) => self.graphics.resize(size),
+            None => self.requested_size = Some(size),
+        }
+    }
+
+    fn exit_if_requested(&self, event_loop: &ActiveEventLoop) {
+        if self.graphics.context().exit_requested() {
+            event_loop.exit();
+        }
+    }
+
+    /// Whether the window is minimized, in which case neither updates nor rendering happen.
+    /// Replays never pause, their updates are recorded.
+    fn paused(&self) -> bool {
+        let ctx = self.graphics.context();
+        !matches!(self.input_mode, InputMode::Replay(_))
+            && (ctx.is_occluded() || ctx.size().min_element() <= 0.0)
+    }
+}
+
+impl<T: State> AppWindow for WindowState<T> {
+    fn event(&mut self, event_loop: &ActiveEventLoop, event: WindowEvent) {
         match event {
-            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::CloseRequested => self.graphics.context().close(),
             WindowEvent::Resized(size) => {
                 self.graphics.resize(size);
-                self.user_state.resize(
-                    self.graphics.context(),
-                    vec2(size.width as f32, size.height as f32),
-                );
+                if let Some(requested) = self.requested_size.take() {
+                    if requested != size {
+                        log::warn!(
+                            "the window is {}x{} instead of the recorded {}x{}",
+                            size.width,
+                            size.height,
+                            requested.width,
+                            requested.height
+                        );
+                    }
+                }
+                self.input(InputEvent::Resized(vec2(
+                    size.width as f32,
+                    size.height as f32,
+                )));
             }
-            WindowEvent::KeyboardInput { event, .. } => match event.physical_key {
-                PhysicalKey::Code(code) => {
-                    self.graphics
-                        .context_mut()
-                        .set_key(code, event.state.is_pressed());
-                    self.user_state
-                        .key(self.graphics.context(), code, event.state.is_pressed())
+            WindowEvent::KeyboardInput { event, .. } => {
+                let pressed = event.state.is_pressed();
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    self.input(InputEvent::Key(code, pressed));
                 }
-                _ => {}
-            },
-            WindowEvent::CursorMoved { position, .. } => self.user_state.cursor(
-                self.graphics.context(),
-                vec2(position.x as f32, position.y as f32),
-            ),
+                self.input(InputEvent::LogicalKey(event.logical_key, pressed));
+                // The IME delivers the text itself, some platforms would send it twice.
+                if !self.graphics.context().is_ime_allowed() {
+                    let text = event
+                        .text
+                        .iter()
+                        .flat_map(|text| text.chars())
+                        .filter(|c| !c.is_control())
+                        .collect::<String>();
+                    if !text.is_empty() {
+                        self.input(InputEvent::Text(text));
+                    }
+                }
+            }
+            WindowEvent::Ime(Ime::Preedit(text, cursor)) => {
+                self.input(InputEvent::ImePreedit(text, cursor))
+            }
+            WindowEvent::Ime(Ime::Commit(text)) => self.input(InputEvent::ImeCommit(text)),
+            WindowEvent::CursorMoved { position, .. } => self.input(InputEvent::Cursor(vec2(
+                position.x as f32,
+                position.y as f32,
+            ))),
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.input(InputEvent::Modifiers(modifiers.state()))
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.input(InputEvent::MouseButton(button, state.is_pressed()))
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => vec2(x, y),
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        vec2(delta.x as f32, delta.y as f32) / PIXELS_PER_LINE
+                    }
+                };
+                self.input(InputEvent::Scroll(delta))
+            }
+            WindowEvent::Touch(touch) => self.input(InputEvent::Touch(Touch {
+                id: touch.id,
+                phase: touch.phase,
+                position: vec2(touch.location.x as f32, touch.location.y as f32),
+                force: touch.force.map(|force| force.normalized() as f32),
+            })),
+            WindowEvent::Focused(focused) => self.input(InputEvent::Focused(focused)),
+            WindowEvent::Occluded(occluded) => self.input(InputEvent::Occluded(occluded)),
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.input(InputEvent::ScaleFactor(scale_factor as f32))
+            }
+            WindowEvent::Moved(position) => {
+                self.input(InputEvent::Moved(ivec2(position.x, position.y)))
+            }
+            WindowEvent::HoveredFile(path) => self.input(InputEvent::HoveredFile(path)),
+            WindowEvent::HoveredFileCancelled => self.input(InputEvent::HoveredFileCancelled),
+            WindowEvent::DroppedFile(path) => self.input(InputEvent::DroppedFile(path)),
+            WindowEvent::RedrawRequested if self.paused() => {}
             WindowEvent::RedrawRequested => match self.graphics.render(&self.user_state) {
                 Ok(_) => {}
                 Err(wgpu::SurfaceError::Lost) => self.graphics.resize_own(),
@@ -69,13 +220,49 @@ impl<T: State> WindowState<T> {
             },
             _ => {}
         }
+        self.exit_if_requested(event_loop);
+    }
+
+    fn device_event(&mut self, event_loop: &ActiveEventLoop, event: DeviceEvent) {
+        // Raw motion reaches every window, only the focused one takes it.
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = event {
+            if !self.graphics.context().is_focused() {
+                return;
+            }
+            self.input(InputEvent::MouseMotion(vec2(x as f32, y as f32)));
+        }
+        self.exit_if_requested(event_loop);
     }
 
-    pub fn update(&mut self) {
+    fn update(&mut self, event_loop: &ActiveEventLoop) {
         let now = Instant::now();
         let delta = now - self.last_time;
         self.last_time = now;
-        self.user_state.update(self.graphics.context(), delta);
+        if self.paused() {
+            return;
+        }
+        if let InputMode::Replay(recording) = &mut self.input_mode {
+            let size = self.graphics.context().size();
+            if !recording.replay_update(self.graphics.context_mut(), &mut self.user_state) {
+                self.graphics.context().close();
+            }
+            let recorded = self.graphics.context().size();
+            if recorded != size {
+                self.request_recorded_size(recorded);
+            }
+        } else {
+            self.input(InputEvent::Update(delta));
+            self.record(RecordingWriter::flush);
+        }
+        self.exit_if_requested(event_loop);
         self.window.request_redraw();
     }
+
+    fn exit(&mut self) {
+        self.record(RecordingWriter::flush);
+    }
+
+    fn context(&self) -> &Context {
+        self.graphics.context()
+    }
 }
@@ -0,0 +1,115 @@
+use std::{path::PathBuf, time::Duration};
+
+use grafx::{
+    glam::*, ActionMap, Binding, Context, Frame, Key, KeyCode, NamedKey, ReplayError, State,
+};
+
+/// Fly camera like the one of the example, without the rendering.
+struct FlyCamera {
+    position: Vec3,
+    yaw: f32,
+    aspect: f32,
+    logical_size: Vec2,
+    actions: ActionMap,
+    shortcuts: Vec<Key>,
+}
+
+impl FlyCamera {
+    fn direction(&self) -> Vec3 {
+        vec3(self.yaw.cos(), 0.0, self.yaw.sin())
+    }
+}
+
+impl State for FlyCamera {
+    fn new(_ctx: &Context) -> Self {
+        Self {
+            position: Vec3::ZERO,
+            yaw: -90.0f32.to_radians(),
+            aspect: 1.0,
+            logical_size: Vec2::ZERO,
+            actions: ActionMap::new().with_axis2d(
+                "move",
+                &[Binding::Key(KeyCode::KeyA)],
+                &[Binding::Key(KeyCode::KeyD)],
+                &[Binding::Key(KeyCode::KeyS)],
+                &[Binding::Key(KeyCode::KeyW)],
+            ),
+            shortcuts: Vec::new(),
+        }
+    }
+
+    fn resize(&mut self, _ctx: &Context, size: Vec2, logical_size: Vec2) {
+        self.aspect = size.x / size.y;
+        self.logical_size = logical_size;
+    }
+
+    fn logical_key(&mut self, _ctx: &Context, key: &Key, pressed: bool) {
+        if pressed {
+            self.shortcuts.push(key.clone());
+        }
+    }
+
+    fn mouse_motion(&mut self, _ctx: &Context, delta: Vec2) {
+        self.yaw += delta.x * 0.002;
+    }
+
+    fn update(&mut self, ctx: &Context, delta: Duration) {
+        let movement = self.actions.axis2d(ctx.input(), "move");
+        let right = self.direction().cross(Vec3::Y).normalize();
+        self.position +=
+            (self.direction() * movement.y + right * movement.x) * 2.0 * delta.as_secs_f32();
+    }
+
+    fn render(&self, _frame: &mut Frame) {}
+}
+
+fn write_recording(name: &str, text: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("grafx-{}-{name}", std::process::id()));
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn fly_camera() {
+    let path = write_recording(
+        "fly_camera",
+        "scale 2\n\
+         resize 800 600\n\
+         key KeyW 1\n\
+         logical 1 char w\n\
+         update 500000000\n\
+         key KeyW 0\n\
+         logical 0 char w\n\
+         logical 1 named Space\n\
+         motion 100 0\n\
+         update 500000000\n",
+    );
+    let camera = match grafx::replay_headless::<FlyCamera>(&path) {
+        Err(ReplayError::NoAdapter) => {
+            eprintln!("skipping the replay, there is no GPU adapter");
+            return;
+        }
+        result => result.unwrap(),
+    };
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(camera.aspect, 800.0 / 600.0);
+    assert_eq!(camera.logical_size, vec2(400.0, 300.0));
+    assert!(camera.position.abs_diff_eq(vec3(0.0, 0.0, -1.0), 1e-6));
+    assert_eq!(
+        camera.shortcuts,
+        [Key::Character("w".into()), Key::Named(NamedKey::Space)]
+    );
+    assert!((camera.yaw - (-90.0f32.to_radians() + 0.2)).abs() < 1e-6);
+}
+
+#[test]
+fn invalid_recording() {
+    let path = write_recording("invalid", "resize 800 600\nkey NoSuchKey 1\n");
+    let result = grafx::replay_headless::<FlyCamera>(&path);
+    std::fs::remove_file(path).unwrap();
+    assert!(matches!(result, Err(ReplayError::Parse { line: 2, .. })));
+    assert!(matches!(
+        grafx::replay_headless::<FlyCamera>("no/such/recording"),
+        Err(ReplayError::Io(_))
+    ));
+}